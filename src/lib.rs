@@ -0,0 +1,5 @@
+//! The viewer is in `main.rs`, the library lets integration tests drive an `App` without a window.
+
+mod rim;
+
+pub use rim::*;
@@ -5,24 +5,174 @@
 extern crate clap;
 
 
-use std::path::*;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
-mod rim;
 use rim::app::App;
-
-use rim::util::*;
+use rim::config::Config;
+use rim::ipc;
+use rim::ipc::Command;
+use rim::stream::FrameFormat;
+use rim::logger;
+use rim::notifications::Severity;
+use rim::paths;
+use rim::paths::{PathOptions, SortOrder};
 
 fn main() {
     let matches = clap_app!(myapp =>
         (version: "0.0.4")
         (author: "Nimaoth")
         (about: "View images")
-        (@arg file: +required +takes_value "Display this file or files in this directory")
+        (@arg file: +takes_value +multiple required_unless[list_bindings session remote] "Display these files, URLs, glob patterns or the files in these directories, - reads from stdin")
+        (@arg recursive: -r --recursive "Also open the files in subdirectories")
+        (@arg max: --max +takes_value "Open at most this many files")
+        (@arg sort: --sort +takes_value possible_values(&["name", "natural", "mtime", "size"]) "Sort all files before opening them, natural sorts numbers by value")
         (@arg floating: -f --float "Open as floating window")
         (@arg size: -s --size +takes_value +multiple #{2, 2} "Size of floating window")
+        (@arg config: -c --config +takes_value "Load key bindings from this config file instead of the default one")
+        (@arg list_bindings: --("list-bindings") "Print all actions and their current key bindings")
+        (@arg session: --session +takes_value "Restore the images from this session file, sessions are also saved to this file")
+        (@arg listen: --listen "Accept remote commands from other instances")
+        (@arg remote: --remote +takes_value "Send a command (open, close, reload or select, followed by a path) to the running instance")
+        (@arg socket: --socket +takes_value "Socket used for remote commands")
+        (@arg stream: --stream +takes_value possible_values(&["png", "length"]) "Read a stream of images from stdin or a named pipe, either concatenated pngs or frames prefixed with their length as big endian u32")
+        (@arg single_instance: --("single-instance") "Open the file in the running instance if there is one, and listen for other instances otherwise")
+        (@arg poll: --poll +takes_value "Check images opened by URL for changes every this many seconds")
+        (@arg continuous: --continuous "Redraw every frame instead of only when something changed")
+        (@arg texture_budget: --("texture-budget") +takes_value "Scale textures down to keep their memory below this many MiB, defaults to 1024")
+        (@arg log_level: --("log-level") +takes_value possible_values(&["off", "error", "warn", "info", "debug", "trace"]) "Only log messages of this level or more severe, defaults to $RIM_LOG or warn")
+        (@arg log_file: --("log-file") +takes_value "Also write the log to this file")
     )
     .get_matches();
 
+    let log_buffer = match logger::init(
+        matches.value_of("log_level").and_then(logger::parse_level),
+        matches.value_of("log_file").map(Path::new)) {
+        Ok(buffer) => Some(buffer),
+        Err(msg) => {
+            eprintln!("{}", msg);
+            None
+        },
+    };
+
+    let config_path = match matches.value_of("config") {
+        Some(path) => Some(PathBuf::from(path)),
+        None => Config::default_path(),
+    };
+    let config = match config_path {
+        Some(path) => match Config::load(&path) {
+            Ok(config) => config,
+            Err(msg) => {
+                log::error!("{}", msg);
+                Config::default()
+            }
+        },
+        None => Config::default(),
+    };
+
+    if matches.is_present("list_bindings") {
+        config.keymap.print_bindings();
+        return;
+    }
+
+    let socket_path = match matches.value_of("socket") {
+        Some(path) => Ok(PathBuf::from(path)),
+        None => ipc::default_socket_path().map_err(|err| format!("No socket for remote commands: {}", err)),
+    };
+    let mut listen = matches.is_present("listen");
+    let mut files: Vec<PathBuf> = match matches.values_of("file") {
+        Some(values) => values.map(PathBuf::from).collect(),
+        None => Vec::new(),
+    };
+
+    if let Some(remote) = matches.value_of("remote") {
+        let command = match Command::parse(remote) {
+            Ok(command) => command.into_absolute(),
+            Err(msg) => {
+                eprintln!("{}", msg);
+                return;
+            }
+        };
+
+        let socket_path = match &socket_path {
+            Ok(path) => path,
+            Err(msg) => {
+                eprintln!("{}", msg);
+                return;
+            }
+        };
+
+        match ipc::send_commands(socket_path, &[command.clone()]) {
+            Ok(results) => {
+                for result in results.into_iter() {
+                    if let Err(msg) = result {
+                        eprintln!("{}", msg);
+                    }
+                }
+                return;
+            },
+
+            // no running instance, open the file in a new one which listens for further commands
+            Err(_) => match command {
+                Command::Open(path) => {
+                    files = vec![path];
+                    listen = true;
+                },
+                _ => {
+                    eprintln!("No running instance is listening on {:?}", socket_path);
+                    return;
+                },
+            },
+        }
+    }
+
+    let path_options = PathOptions {
+        recursive   : matches.is_present("recursive"),
+        max_count   : match matches.value_of("max").map(str::parse::<usize>) {
+            Some(Ok(max_count)) => Some(max_count),
+            Some(Err(_)) => {
+                eprintln!("Max must be a number");
+                return;
+            },
+            None => None,
+        },
+        sort        : matches.value_of("sort").and_then(SortOrder::from_name),
+    };
+    let expanded = paths::expand_paths(&files, &path_options);
+    for msg in expanded.errors.iter() {
+        log::warn!("{}", msg);
+    }
+    if expanded.skipped > 0 {
+        log::info!("Skipped {} files which are not images", expanded.skipped);
+    }
+    if !files.is_empty() && expanded.paths.is_empty() {
+        if expanded.errors.is_empty() {
+            eprintln!("No images found");
+        }
+        return;
+    }
+    let paths = expanded.paths;
+
+    if matches.is_present("single_instance") {
+        let mut commands: Vec<Command> = paths.iter()
+            .map(|path| Command::Open(path.clone()).into_absolute())
+            .collect();
+        commands.push(Command::Raise);
+
+        let results = socket_path.as_ref().ok().and_then(|path| ipc::send_commands(path, &commands).ok());
+        if let Some(results) = results {
+            for result in results.into_iter() {
+                if let Err(msg) = result {
+                    eprintln!("{}", msg);
+                }
+            }
+            return;
+        }
+
+        // this is the first instance
+        listen = true;
+    }
+
     let mut floating = false;
     let (mut width, mut height) = (1000, 900);
     if matches.is_present("floating") {
@@ -31,44 +181,71 @@ fn main() {
     match matches.values_of("size") {
         Some(values) => {
             let values: Vec<_> = values.collect();
-            width = values[0].parse().expect("Width must be a number");
-            height = values[1].parse().expect("Height must be a number");
+            match (values[0].parse(), values[1].parse()) {
+                (Ok(w), Ok(h)) => {
+                    width = w;
+                    height = h;
+                },
+                _ => {
+                    eprintln!("Width and height must be numbers");
+                    return;
+                },
+            }
         },
         None => {},
     };
 
-    let mut app = App::new(floating, width, height);
+    let mut app = App::new(floating, width, height, config.keymap);
+    app.set_edge_behavior(config.edges);
+    if let Some(buffer) = log_buffer {
+        app.set_log_buffer(buffer);
+    }
 
-    let path = get_absolute_path(&PathBuf::from(matches.value_of("file").unwrap()));
-    if Path::is_file(&path) {
-        match app.open_image(Path::new(&path), false) {
-            Ok(_) => {},
-            Err(_) => {
-                eprintln!("Failed to load image {:?}", path);
+    if let Some(format) = matches.value_of("stream").and_then(FrameFormat::from_name) {
+        app.set_stream_format(format);
+    }
+
+    if let Some(poll) = matches.value_of("poll") {
+        match poll.parse::<f32>() {
+            Ok(seconds) if seconds > 0.0 => app.set_poll_interval(Duration::from_secs_f32(seconds)),
+            _ => {
+                eprintln!("Poll interval must be a positive number of seconds");
+                return;
             },
         }
-    } else if Path::is_dir(&path) {
-        match std::fs::read_dir(path) {
-            Ok(dir) => {
-                for image_path in dir.into_iter() {
-                    match image_path {
-                        Ok(path) => {
-                            let path = get_absolute_path(&path.path());
-                            match app.open_image(&path, false) {
-                                Ok(_) => {},
-                                Err(_) => eprintln!("Failed to load image '{:?}'", &path),
-                            }
-                        }
-                        Err(msg) => eprintln!("Error: {}", msg),
-                    }
-                }
+    }
+
+    app.set_continuous_redraw(matches.is_present("continuous"));
+
+    if let Some(budget) = matches.value_of("texture_budget") {
+        match budget.parse::<usize>() {
+            Ok(mib) if mib > 0 => app.set_texture_budget(mib * 1024 * 1024),
+            _ => {
+                eprintln!("Texture budget must be a positive number of MiB");
+                return;
+            },
+        }
+    }
+
+    if listen {
+        match socket_path.and_then(|path| app.start_ipc_server(&path)) {
+            Ok(_) => {},
+            Err(msg) => app.notify(Severity::Error, msg),
+        }
+    }
+
+    if let Some(session_path) = matches.value_of("session") {
+        let session_path = PathBuf::from(session_path);
+        if session_path.exists() {
+            match app.load_session(&session_path) {
+                Ok(_) => {},
+                Err(msg) => app.notify(Severity::Error, msg),
             }
-            Err(msg) => eprintln!("Failed to load files in directory: {}", msg),
         }
-    } else {
-        eprintln!("path is not a file or directory: {:?}", path);
-        return;
+        app.set_session_path(session_path);
     }
 
+    app.open_paths(&paths);
+
     app.run();
 }
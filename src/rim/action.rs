@@ -0,0 +1,251 @@
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Action {
+    Quit,
+    OpenFile,
+    CloseView,
+    ToggleFullscreen,
+    Reload,
+    ToggleHistory,
+    ToggleTitlebars,
+    CommandPalette,
+    SaveSession,
+    LoadSession,
+    DismissNotifications,
+    ToggleNotificationLog,
+    ToggleLog,
+    ToggleDebugOverlay,
+
+    // filter method
+    FilterNearest,
+    FilterLinear,
+    FilterTrilinear,
+    FilterLanczos,
+
+    // layout direction
+    LayoutAuto,
+    LayoutHorizontal,
+    LayoutVertical,
+
+    // layout
+    LayoutGrid,
+    LayoutRows,
+    LayoutMasonry,
+    LayoutFocus,
+    LayoutSplit,
+
+    // split layout
+    SplitHorizontal,
+    SplitVertical,
+    MergePanes,
+    GrowPane,
+    ShrinkPane,
+
+    // groups
+    NextGroup,
+    PreviousGroup,
+    NewGroup,
+    MoveToNextGroup,
+    MoveToPreviousGroup,
+
+    // move selection
+    SelectUp,
+    SelectDown,
+    SelectLeft,
+    SelectRight,
+
+    // move selected
+    MoveUp,
+    MoveDown,
+    MoveLeft,
+    MoveRight,
+
+    // pan and zoom the selected view, these are applied every frame while held
+    PanUp,
+    PanDown,
+    PanLeft,
+    PanRight,
+    ZoomIn,
+    ZoomOut,
+    ResetZoom,
+}
+
+impl Action {
+    pub const ALL: &'static [Action] = &[
+        Action::Quit,
+        Action::OpenFile,
+        Action::CloseView,
+        Action::ToggleFullscreen,
+        Action::Reload,
+        Action::ToggleHistory,
+        Action::ToggleTitlebars,
+        Action::CommandPalette,
+        Action::SaveSession,
+        Action::LoadSession,
+        Action::DismissNotifications,
+        Action::ToggleNotificationLog,
+        Action::ToggleLog,
+        Action::ToggleDebugOverlay,
+        Action::FilterNearest,
+        Action::FilterLinear,
+        Action::FilterTrilinear,
+        Action::FilterLanczos,
+        Action::LayoutAuto,
+        Action::LayoutHorizontal,
+        Action::LayoutVertical,
+        Action::LayoutGrid,
+        Action::LayoutRows,
+        Action::LayoutMasonry,
+        Action::LayoutFocus,
+        Action::LayoutSplit,
+        Action::SplitHorizontal,
+        Action::SplitVertical,
+        Action::MergePanes,
+        Action::GrowPane,
+        Action::ShrinkPane,
+        Action::NextGroup,
+        Action::PreviousGroup,
+        Action::NewGroup,
+        Action::MoveToNextGroup,
+        Action::MoveToPreviousGroup,
+        Action::SelectUp,
+        Action::SelectDown,
+        Action::SelectLeft,
+        Action::SelectRight,
+        Action::MoveUp,
+        Action::MoveDown,
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::PanUp,
+        Action::PanDown,
+        Action::PanLeft,
+        Action::PanRight,
+        Action::ZoomIn,
+        Action::ZoomOut,
+        Action::ResetZoom,
+    ];
+
+    /// Name used in the config file.
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Quit                => "quit",
+            Action::OpenFile            => "open_file",
+            Action::CloseView           => "close_view",
+            Action::ToggleFullscreen    => "toggle_fullscreen",
+            Action::Reload              => "reload",
+            Action::ToggleHistory       => "toggle_history",
+            Action::ToggleTitlebars     => "toggle_titlebars",
+            Action::CommandPalette      => "command_palette",
+            Action::SaveSession         => "save_session",
+            Action::LoadSession         => "load_session",
+            Action::DismissNotifications => "dismiss_notifications",
+            Action::ToggleNotificationLog => "toggle_notification_log",
+            Action::ToggleLog           => "toggle_log",
+            Action::ToggleDebugOverlay  => "toggle_debug_overlay",
+            Action::FilterNearest       => "filter_nearest",
+            Action::FilterLinear        => "filter_linear",
+            Action::FilterTrilinear     => "filter_trilinear",
+            Action::FilterLanczos       => "filter_lanczos",
+            Action::LayoutAuto          => "layout_auto",
+            Action::LayoutHorizontal    => "layout_horizontal",
+            Action::LayoutVertical      => "layout_vertical",
+            Action::LayoutGrid          => "layout_grid",
+            Action::LayoutRows          => "layout_rows",
+            Action::LayoutMasonry       => "layout_masonry",
+            Action::LayoutFocus         => "layout_focus",
+            Action::LayoutSplit         => "layout_split",
+            Action::SplitHorizontal     => "split_horizontal",
+            Action::SplitVertical       => "split_vertical",
+            Action::MergePanes          => "merge_panes",
+            Action::GrowPane            => "grow_pane",
+            Action::ShrinkPane          => "shrink_pane",
+            Action::NextGroup           => "next_group",
+            Action::PreviousGroup       => "previous_group",
+            Action::NewGroup            => "new_group",
+            Action::MoveToNextGroup     => "move_to_next_group",
+            Action::MoveToPreviousGroup => "move_to_previous_group",
+            Action::SelectUp            => "select_up",
+            Action::SelectDown          => "select_down",
+            Action::SelectLeft          => "select_left",
+            Action::SelectRight         => "select_right",
+            Action::MoveUp              => "move_up",
+            Action::MoveDown            => "move_down",
+            Action::MoveLeft            => "move_left",
+            Action::MoveRight           => "move_right",
+            Action::PanUp               => "pan_up",
+            Action::PanDown             => "pan_down",
+            Action::PanLeft             => "pan_left",
+            Action::PanRight            => "pan_right",
+            Action::ZoomIn              => "zoom_in",
+            Action::ZoomOut             => "zoom_out",
+            Action::ResetZoom           => "reset_zoom",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Action::Quit                => "Quit",
+            Action::OpenFile            => "Open file",
+            Action::CloseView           => "Close image",
+            Action::ToggleFullscreen    => "Toggle fullscreen",
+            Action::Reload              => "Reload selected image from disk",
+            Action::ToggleHistory       => "Toggle history of selected image",
+            Action::ToggleTitlebars     => "Toggle titlebars",
+            Action::CommandPalette      => "Show command palette",
+            Action::SaveSession         => "Save session",
+            Action::LoadSession         => "Load session",
+            Action::DismissNotifications => "Dismiss all notifications",
+            Action::ToggleNotificationLog => "Show notification log",
+            Action::ToggleLog           => "Show log",
+            Action::ToggleDebugOverlay  => "Show texture count and memory",
+            Action::FilterNearest       => "Switch to nearest filtering",
+            Action::FilterLinear        => "Switch to linear filtering",
+            Action::FilterTrilinear     => "Switch to trilinear filtering",
+            Action::FilterLanczos       => "Switch to Lanczos filtering",
+            Action::LayoutAuto          => "Auto layout",
+            Action::LayoutHorizontal    => "Horizontal layout",
+            Action::LayoutVertical      => "Vertical layout",
+            Action::LayoutGrid          => "Grid layout, cells of the same size",
+            Action::LayoutRows          => "Row layout, cells sized by aspect ratio",
+            Action::LayoutMasonry       => "Masonry layout, columns of cells sized by aspect ratio",
+            Action::LayoutFocus         => "Focus layout, selected image large with thumbnails",
+            Action::LayoutSplit         => "Split layout, panes arranged by hand",
+            Action::SplitHorizontal     => "Split pane side by side with the next image",
+            Action::SplitVertical       => "Split pane above the next image",
+            Action::MergePanes          => "Merge panes into the surrounding split",
+            Action::GrowPane            => "Grow selected pane",
+            Action::ShrinkPane          => "Shrink selected pane",
+            Action::NextGroup           => "Show next group",
+            Action::PreviousGroup       => "Show previous group",
+            Action::NewGroup            => "Add an empty group",
+            Action::MoveToNextGroup     => "Move selected image to next group",
+            Action::MoveToPreviousGroup => "Move selected image to previous group",
+            Action::SelectUp            => "Select image above",
+            Action::SelectDown          => "Select image below",
+            Action::SelectLeft          => "Select image left",
+            Action::SelectRight         => "Select image right",
+            Action::MoveUp              => "Move selected image up",
+            Action::MoveDown            => "Move selected image down",
+            Action::MoveLeft            => "Move selected image left",
+            Action::MoveRight           => "Move selected image right",
+            Action::PanUp               => "Pan up",
+            Action::PanDown             => "Pan down",
+            Action::PanLeft             => "Pan left",
+            Action::PanRight            => "Pan right",
+            Action::ZoomIn              => "Zoom in",
+            Action::ZoomOut             => "Zoom out",
+            Action::ResetZoom           => "Reset zoom",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Action> {
+        Action::ALL.iter().copied().find(|action| action.name() == name)
+    }
+
+    /// Continuous actions are checked every frame while their keys are held down
+    /// instead of being triggered once per key press.
+    pub fn is_continuous(self) -> bool {
+        matches!(self,
+            Action::PanUp | Action::PanDown | Action::PanLeft | Action::PanRight |
+            Action::ZoomIn | Action::ZoomOut)
+    }
+}
@@ -1,6 +1,9 @@
 use sdl2;
 
 use std::boxed::Box;
+use std::collections::{HashMap, VecDeque};
+use std::io::Read;
+use std::sync::Arc;
 use std::path::*;
 use std::sync::mpsc::channel;
 use std::time::Duration;
@@ -9,28 +12,93 @@ use std::time::{SystemTime, UNIX_EPOCH};
 
 use notify::{Watcher, watcher};
 
+use super::action::Action;
+use super::keymap::KeyMap;
 use super::view::{View, FilterMethod};
-use super::image::Image;
-use super::layout::{Layout, GridLayout, LayoutDirection};
+use super::image::{Image, texture_stats};
+use super::image_cache::ImageCache;
+use super::group::{self, ViewGroup};
+use super::layout::{self, EdgeBehavior, LayoutDirection, LayoutKind};
+use super::split_layout::{Divider, SplitLayout};
 use super::open_file_dialog::OpenFileDialog;
+use super::command_palette::CommandPalette;
+use super::error::RimError;
+use super::notifications::{Notifications, Severity};
+use super::logger::{LogBuffer, LogViewer};
+use super::debug_overlay::DebugOverlay;
+use super::texture_budget::TextureBudget;
+use super::renderer;
+use super::gl_renderer::GlRenderer;
+use super::software_renderer::SoftwareRenderer;
+use super::headless::Headless;
+use super::session::{Session, WindowState, GroupState, LayoutState, ViewState};
+use super::ipc::{IpcServer, Command};
+use super::wake;
+use super::stream::{FrameStream, FrameFormat, is_stdin, is_fifo};
+use super::paths::{expand_paths, PathOptions};
+use super::remote::{RemoteImages, RemoteHandle, RemoteEvent, is_url};
 use super::util::*;
+use super::vec::Vec2;
 
-pub struct App {
-    views           : Vec<View>,
-    next_view_id    : u32,
-    layout          : Box<dyn Layout>,
-    layout_direction: LayoutDirection,
-    auto_layout_dir : bool,
+/// How long the loop waits for input before it checks files, remote commands and downloads again,
+/// only if background threads can't wake it, see `wake`.
+const IDLE_WAIT: u32 = 100;
+
+/// Frames drawn after something changed, imgui needs a few frames to settle e.g. hovered items and new windows.
+const FRAMES_AFTER_CHANGE: u32 = 3;
+
+/// Estimated texture memory used before textures are scaled down, unless set with `--texture-budget`.
+pub const DEFAULT_TEXTURE_BUDGET: usize = 1024 * 1024 * 1024;
+
+/// Background of the window where no view is.
+const CLEAR_COLOR: [f32; 4] = [0.3, 0.3, 0.5, 1.0];
+
+/// Share of its split a pane of the split layout grows or shrinks by with `GrowPane` and `ShrinkPane`.
+const PANE_RESIZE_STEP: f32 = 0.05;
+
+/// Height of the tabs above the views, shown when there's more than one group.
+const TAB_BAR_HEIGHT: i32 = 24;
 
+/// The SDL window an app is shown in, with the GL context the renderer draws with.
+struct AppWindow {
     sdl             : sdl2::Sdl,
     _video_subsystem: sdl2::VideoSubsystem,
     window          : sdl2::video::Window,
     _gl_context     : sdl2::video::GLContext,
-    imgui           : imgui::Context,
     imgui_sdl2      : imgui_sdl2::ImguiSdl2,
-    opengl_renderer : imgui_opengl_renderer::Renderer,
+}
+
+/// Where the app gets its input from and shows its frames.
+enum Platform {
+    Window(AppWindow),
+    /// No window, input comes from the events passed to `App::step` and frames are drawn by the software renderer.
+    Headless(Headless),
+}
+
+/// What the events of a frame asked for, done while drawing it.
+#[derive(Default)]
+struct FrameRequests {
+    open_file_open_dialog   : bool,
+    close_view              : bool,
+    mouse_moved             : bool,
+    right_clicked           : bool,
+}
+
+pub struct App {
+    /// Views of all groups, one group after the other.
+    views           : Vec<View>,
+    next_view_id    : u32,
+    groups          : Vec<ViewGroup>,
+    /// Index of the group which is shown.
+    group           : usize,
+    /// Divider of the split layout which is being dragged.
+    dragged_divider : Option<Divider>,
+    edge_behavior   : EdgeBehavior,
+
+    platform        : Platform,
+    imgui           : imgui::Context,
 
-    dir_watcher     : notify::RecommendedWatcher,
+    dir_watcher     : Option<notify::RecommendedWatcher>,
     dir_watcher_recv: mpsc::Receiver<notify::DebouncedEvent>,
 
     show_titlebars  : bool,
@@ -40,15 +108,37 @@ pub struct App {
 
     maximized       : bool,
     open_file_dialog: OpenFileDialog,
-
-    pub error_msg   : Option<String>,
+    command_palette : CommandPalette,
+    keymap          : KeyMap,
+    session_path    : Option<PathBuf>,
+    ipc_server      : Option<IpcServer>,
+    streams         : Vec<FrameStream>,
+    stream_format   : Option<FrameFormat>,
+    remote_images   : RemoteImages,
+    remote_handles  : HashMap<String, RemoteHandle>,
+    poll_interval   : Option<Duration>,
+
+    notifications   : Notifications,
+    log_viewer      : Option<LogViewer>,
+    debug_overlay   : DebugOverlay,
+    texture_budget  : TextureBudget,
+    continuous_redraw: bool,
+
+    image_cache     : ImageCache,
+    /// Images decoded in the background, opened in this order once they are done.
+    pending_images  : VecDeque<PathBuf>,
+    loaded_images   : HashMap<PathBuf, Result<Arc<Image>, RimError>>,
 }
 
 impl App {
-    pub fn new(floating: bool, width: i32, height: i32) -> App {
+    pub fn new(floating: bool, width: i32, height: i32, keymap: KeyMap) -> App {
         let sdl = sdl2::init().unwrap();
         
         let video_subsystem = sdl.video().unwrap();
+        if cfg!(debug_assertions) {
+            // Drivers only report detailed messages through KHR_debug for debug contexts
+            video_subsystem.gl_attr().set_context_flags().debug().set();
+        }
         let mut window = video_subsystem.window("Rim", width as u32, height as u32);
         window.opengl();
         
@@ -61,36 +151,60 @@ impl App {
         let gl_context = window
             .gl_create_context()
             .expect("Couldn't create GL context");
-        gl::load_with(|s| video_subsystem.gl_get_proc_address(s) as _);
-
-        let mut imgui = imgui::Context::create();
-        imgui.set_ini_filename(None);
-        imgui.style_mut().window_rounding = 0.0;
-        imgui.style_mut().window_border_size = 1.0;
+        if let Err(err) = video_subsystem.gl_set_swap_interval(sdl2::video::SwapInterval::VSync) {
+            log::warn!("Failed to enable vsync: {}", err);
+        }
+        if let Err(err) = sdl.event().and_then(|events| wake::register(&events)) {
+            log::warn!("Failed to register the wake event, checking for updates periodically: {}", err);
+        }
 
+        let mut imgui = create_imgui();
         let imgui_sdl2 = imgui_sdl2::ImguiSdl2::new(&mut imgui, &window);
-        let renderer = imgui_opengl_renderer::Renderer::new(&mut imgui, |s| {
-            video_subsystem.gl_get_proc_address(s) as _
+        renderer::set_current(Some(Box::new(GlRenderer::new(&mut imgui, &video_subsystem))));
+
+        let platform = Platform::Window(AppWindow {
+            sdl             : sdl,
+            _video_subsystem: video_subsystem,
+            window          : window,
+            _gl_context     : gl_context,
+            imgui_sdl2      : imgui_sdl2,
         });
+        App::with_platform(platform, imgui, keymap)
+    }
+
+    /// Creates an app without a window, which draws with the software renderer on this thread.
+    /// Nothing happens until `step` is called, e.g. by tests.
+    pub fn headless(width: u32, height: u32, keymap: KeyMap) -> App {
+        let mut imgui = create_imgui();
+        let headless = Headless::new(&mut imgui, width, height);
+        renderer::set_current(Some(Box::new(SoftwareRenderer::new(&mut imgui))));
 
+        App::with_platform(Platform::Headless(headless), imgui, keymap)
+    }
+
+    fn with_platform(platform: Platform, imgui: imgui::Context, keymap: KeyMap) -> App {
         let (watch_send, watch_recv) = channel();
-        let watcher = watcher(watch_send, Duration::from_millis(500)).unwrap();
+        let watch_recv = wake::forward(watch_recv);
+        let mut notifications = Notifications::new();
+        let watcher = match watcher(watch_send, Duration::from_millis(500)) {
+            Ok(watcher) => Some(watcher),
+            Err(err) => {
+                notifications.warning(RimError::watcher(None, err).to_string());
+                None
+            },
+        };
 
         App {
             views           : Vec::new(),
             next_view_id    : 0,
 
-            layout          : GridLayout::new(),
-            layout_direction: LayoutDirection::Vertical,
-            auto_layout_dir : true,
+            groups          : vec![ViewGroup::new(group::default_name(&[]))],
+            group           : 0,
+            dragged_divider : None,
+            edge_behavior   : EdgeBehavior::Wrap,
 
-            sdl             : sdl,
-            _video_subsystem: video_subsystem,
-            window          : window,
-            _gl_context     : gl_context,
+            platform        : platform,
             imgui           : imgui,
-            imgui_sdl2      : imgui_sdl2,
-            opengl_renderer : renderer,
 
             dir_watcher     : watcher,
             dir_watcher_recv: watch_recv,
@@ -102,580 +216,1558 @@ impl App {
             maximized       : false,
 
             open_file_dialog: OpenFileDialog::new(),
+            command_palette : CommandPalette::new(),
+            keymap          : keymap,
+            session_path    : None,
+            ipc_server      : None,
+            streams         : Vec::new(),
+            stream_format   : None,
+            remote_images   : RemoteImages::new(),
+            remote_handles  : HashMap::new(),
+            poll_interval   : None,
+
+            notifications   : notifications,
+            log_viewer      : None,
+            debug_overlay   : DebugOverlay::new(),
+            texture_budget  : TextureBudget::new(DEFAULT_TEXTURE_BUDGET),
+            continuous_redraw: false,
+
+            image_cache     : ImageCache::new(),
+            pending_images  : VecDeque::new(),
+            loaded_images   : HashMap::new(),
+        }
+    }
 
-            error_msg       : None,
+    /// Size of the window in screen coordinates.
+    fn window_size(&self) -> (u32, u32) {
+        match &self.platform {
+            Platform::Window(window) => window.window.size(),
+            Platform::Headless(headless) => headless.size(),
         }
     }
 
-    pub fn open_image(&mut self, path: &Path, enable_history: bool) -> Result<usize, ()> {
-        println!("open {:?}", path);
-        let path = get_absolute_path(path);
+    /// Size of the window in pixels, larger than `window_size` on high DPI displays.
+    fn drawable_size(&self) -> (u32, u32) {
+        match &self.platform {
+            Platform::Window(window) => window.window.drawable_size(),
+            Platform::Headless(headless) => headless.size(),
+        }
+    }
 
-        match self.find_image_by_path(&path) {
-            Some(index) => Ok(index),
-            None => {
-                match Image::new(&path) {
-                    Ok(image) => {
-                        let id = self.next_view_id;
-                        self.next_view_id += 1;
-                        self.views.push(View::new(id, image.clone(), enable_history));
-                        self.dir_watcher.watch(path, notify::RecursiveMode::NonRecursive).unwrap_or(());
-                        Ok(self.views.len() - 1)
-                    },
-                    Err(msg) => {
-                        self.error_msg = Some(msg);
-                        Err(())
-                    },
-                }
-            },
+    fn set_window_size(&mut self, width: u32, height: u32) {
+        match &mut self.platform {
+            Platform::Window(window) => window.window.set_size(width, height).unwrap_or(()),
+            Platform::Headless(headless) => headless.set_size(width, height),
         }
     }
 
-    fn find_image_by_path(&mut self, path: &Path) -> Option<usize> {
-        for (i, view) in self.views.iter().enumerate() {
-            if !view.is_frozen() && view.image.path == path {
-                return Some(i);
-            }
+    /// Opens an image, or returns the index of its view if it is already open.
+    pub fn open_image(&mut self, path: &Path, enable_history: bool) -> Result<usize, RimError> {
+        log::debug!("open {:?}", path);
+        let remote = path.to_str().is_some_and(is_url);
+        let path = if remote { path.to_owned() } else { get_absolute_path(path)? };
+
+        if let Some(index) = self.find_image_by_path(self.group, &path) {
+            return Ok(index);
         }
 
-        None
+        let image = self.image_cache.load(&path)?;
+        if !remote {
+            self.watch(&path);
+        }
+        Ok(self.push_view(image, enable_history))
     }
 
-    fn open_file_open_dialog(&mut self) {
-        let path = if self.selected < self.views.len() {
-            let sel_path: &Path = &self.views[self.selected].image.path;
-            match sel_path.parent() {
-                Some(parent) => parent.to_str().unwrap().to_owned(),
-                None => sel_path.to_str().unwrap().to_owned(),
+    /// Opens images without waiting for them to be decoded. Files are decoded on worker threads and shown in the
+    /// given order once they are done, everything else is opened like `open_path` does. Errors are reported as notifications.
+    pub fn open_paths(&mut self, paths: &[PathBuf]) {
+        for path in paths.iter() {
+            let is_file = !is_stdin(path) && !path.to_str().is_some_and(is_url) && !is_fifo(path) && path.is_file();
+            let result = if is_file {
+                get_absolute_path(path).map(|path| self.open_image_in_background(path))
+            } else {
+                self.open_path(path)
+            };
+            if let Err(err) = result {
+                self.notifications.error(err.to_string());
             }
-        } else {
-            std::env::current_dir().unwrap().to_str().unwrap().to_owned()
-        };
-        self.open_file_dialog.open(path);
+        }
     }
 
-    fn handle_event(&mut self, scancode: sdl2::keyboard::Scancode, keymod: sdl2::keyboard::Mod, close_view: &mut bool, open_file_open_dialog: &mut bool) -> bool {
-        use sdl2::keyboard::*;
+    fn open_image_in_background(&mut self, path: PathBuf) {
+        if self.find_image_by_path(self.group, &path).is_some() || self.pending_images.contains(&path) {
+            return;
+        }
+        self.image_cache.load_in_background(path.clone());
+        self.pending_images.push_back(path);
+    }
 
-        let ctrl = Mod::LCTRLMOD | Mod::RCTRLMOD;
-        let shift = Mod::LSHIFTMOD | Mod::RSHIFTMOD;
+    /// Opens the images decoded in the background since the last call, returns whether there were any.
+    fn open_loaded_images(&mut self) -> bool {
+        let mut changed = false;
+        while let Some((path, result)) = self.image_cache.try_recv() {
+            // images still decoding when a session was loaded aren't opened anymore
+            if self.pending_images.contains(&path) {
+                self.loaded_images.insert(path, result);
+            }
+            changed = true;
+        }
 
-        if keymod.intersects(ctrl) && keymod.intersects(shift) {
-            match scancode {
-                // move selected
-                Scancode::Up => {
-                    if self.selected < self.views.len() {
-                        let new_selected = self.layout.get_next_index(self.views.len(), self.selected, 0, -1, self.layout_direction);
-                        self.views.swap(self.selected, new_selected);
-                        self.selected = new_selected;
-                    }
-                },
-                Scancode::Down => {
-                    if self.selected < self.views.len() {
-                        let new_selected = self.layout.get_next_index(self.views.len(), self.selected, 0, 1, self.layout_direction);
-                        self.views.swap(self.selected, new_selected);
-                        self.selected = new_selected;
-                    }
-                },
-                Scancode::Left => {
-                    if self.selected < self.views.len() {
-                        let new_selected = self.layout.get_next_index(self.views.len(), self.selected, -1, 0, self.layout_direction);
-                        self.views.swap(self.selected, new_selected);
-                        self.selected = new_selected;
-                    }
+        loop {
+            let result = match self.pending_images.front() {
+                Some(path) => match self.loaded_images.remove(path) {
+                    Some(result) => result,
+                    None => break,
                 },
-                Scancode::Right => {
-                    if self.selected < self.views.len() {
-                        let new_selected = self.layout.get_next_index(self.views.len(), self.selected, 1, 0, self.layout_direction);
-                        self.views.swap(self.selected, new_selected);
-                        self.selected = new_selected;
-                    }
+                None => break,
+            };
+            let path = self.pending_images.pop_front().unwrap();
+
+            match result {
+                Ok(image) => if self.find_image_by_path(self.group, &path).is_none() {
+                    self.watch(&path);
+                    self.push_view(image, false);
                 },
+                Err(err) => self.notifications.error(err.to_string()),
+            }
+        }
+
+        changed
+    }
+
+    /// Opens an image from encoded bytes, `path` only identifies the image, e.g. `-` for stdin.
+    pub fn open_image_from_memory(&mut self, path: &Path, data: &[u8], enable_history: bool) -> Result<usize, RimError> {
+        let image = Image::from_memory(path, data)?;
+        Ok(self.push_view(image, enable_history))
+    }
+
+    /// Reloads the image whenever the file changes, failing to watch it is only reported.
+    fn watch(&mut self, path: &Path) {
+        let result = match &mut self.dir_watcher {
+            Some(watcher) => watcher.watch(path, notify::RecursiveMode::NonRecursive),
+            None => return,
+        };
+        if let Err(err) = result {
+            self.notifications.warning(RimError::watcher(Some(path), err).to_string());
+        }
+    }
+
+    /// Records of this buffer are shown in the log viewer.
+    pub fn set_log_buffer(&mut self, buffer: Arc<LogBuffer>) {
+        self.log_viewer = Some(LogViewer::new(buffer));
+    }
+
+    /// Shows a toast, and adds the message to the notification log.
+    pub fn notify(&mut self, severity: Severity, msg: String) {
+        self.notifications.push(severity, msg);
+    }
+
+    /// Shows the frames read from stdin (`-`) or a named pipe in a single view.
+    pub fn open_stream(&mut self, path: &Path, format: FrameFormat) {
+        self.streams.push(FrameStream::open(path, format));
+    }
 
-                //
-                _ => return false,
+    /// Streams are read in this format, without a format stdin is read as a single image.
+    pub fn set_stream_format(&mut self, format: FrameFormat) {
+        self.stream_format = Some(format);
+    }
+
+    /// Downloads an image in the background, it is shown once the download finished.
+    /// With a poll interval the URL is checked for changes while the image is open.
+    pub fn open_url(&mut self, url: &str) {
+        if self.remote_handles.contains_key(url) {
+            // downloaded for another group already, the download keeps updating all views
+            let path = PathBuf::from(url);
+            if self.find_image_by_path(self.group, &path).is_none() && !self.live_views_by_path(&path).is_empty() {
+                if let Err(err) = self.open_image(&path, false) {
+                    self.notifications.error(err.to_string());
+                }
             }
-        } else if keymod.intersects(ctrl) {
-            match scancode {
-                Scancode::R => if self.selected < self.views.len() {
-                    match self.views[self.selected].reload() {
-                        Err(msg) => self.error_msg = Some(msg),
-                        Ok(_) => {},
-                    }
-                },
+            return;
+        }
+        let handle = self.remote_images.fetch(url, self.poll_interval);
+        self.remote_handles.insert(url.to_owned(), handle);
+    }
 
-                // filter method
-                Scancode::L => if self.selected < self.views.len() {
-                    self.views[self.selected].set_filter_menthod(FilterMethod::Linear);
-                },
-                Scancode::N => if self.selected < self.views.len() {
-                    self.views[self.selected].set_filter_menthod(FilterMethod::Nearest);
-                },
+    /// URLs opened after this are checked for changes every `interval`.
+    pub fn set_poll_interval(&mut self, interval: Duration) {
+        self.poll_interval = Some(interval);
+    }
 
-                // layout direction 
-                Scancode::H => {
-                    self.auto_layout_dir = false;
-                    self.layout_direction = LayoutDirection::Horizontal;
-                },
-                Scancode::V => {
-                    self.auto_layout_dir = false;
-                    self.layout_direction = LayoutDirection::Vertical;
-                },
-                Scancode::A => self.auto_layout_dir = true,
+    /// Whether moving the selection past the last view of a row or column continues at its other end.
+    pub fn set_edge_behavior(&mut self, edge_behavior: EdgeBehavior) {
+        self.edge_behavior = edge_behavior;
+    }
 
-                // close selected
-                Scancode::W => *close_view = true,
+    /// Draws every frame, even if nothing changed. By default the loop waits for input or other changes.
+    pub fn set_continuous_redraw(&mut self, continuous: bool) {
+        self.continuous_redraw = continuous;
+    }
 
-                // open new
-                Scancode::O => *open_file_open_dialog = true,
+    fn handle_remote_event(&mut self, event: RemoteEvent) {
+        match event {
+            RemoteEvent::Downloaded { url } => {
+                // the view was closed while downloading
+                if !self.remote_handles.contains_key(&url) {
+                    return;
+                }
+                self.reload_path(&PathBuf::from(&url), None);
+            },
+            RemoteEvent::Failed { url, msg } => if self.remote_handles.contains_key(&url) {
+                self.notifications.error(msg);
+            },
+        }
+    }
 
-                // maximize
-                Scancode::M => {
-                    if self.maximized {
-                        self.window.set_fullscreen(sdl2::video::FullscreenType::Off).unwrap_or(());
-                    } else {
-                        self.window.set_fullscreen(sdl2::video::FullscreenType::True).unwrap_or(());
-                    }
-                    self.maximized = self.window.fullscreen_state() == sdl2::video::FullscreenType::True;
-                },
+    /// Adds a view to the group which is shown.
+    fn push_view(&mut self, image: Arc<Image>, enable_history: bool) -> usize {
+        self.push_view_to_group(self.group, image, enable_history)
+    }
 
-                // move selection
-                Scancode::Up => if self.selected < self.views.len() {
-                    self.views[self.selected].selected = false;
-                    self.selected = self.layout.get_next_index(self.views.len(), self.selected, 0, -1, self.layout_direction);
-                    self.views[self.selected].selected = true;
-                },
-                Scancode::Down => if self.selected < self.views.len() {
-                    self.views[self.selected].selected = false;
-                    self.selected = self.layout.get_next_index(self.views.len(), self.selected, 0, 1, self.layout_direction);
-                    self.views[self.selected].selected = true;
-                },
-                Scancode::Left => if self.selected < self.views.len() {
-                    self.views[self.selected].selected = false;
-                    self.selected = self.layout.get_next_index(self.views.len(), self.selected, -1, 0, self.layout_direction);
-                    self.views[self.selected].selected = true;
-                },
-                Scancode::Right => if self.selected < self.views.len() {
-                    self.views[self.selected].selected = false;
-                    self.selected = self.layout.get_next_index(self.views.len(), self.selected, 1, 0, self.layout_direction);
-                    self.views[self.selected].selected = true;
-                },
+    fn push_view_to_group(&mut self, group: usize, image: Arc<Image>, enable_history: bool) -> usize {
+        let id = self.next_view_id;
+        self.next_view_id += 1;
 
-                //
-                _ => return false,
-            }
-        } else if keymod.intersects(shift) {
-            match scancode {
-                // move selected
-                Scancode::I => {
-                    if self.selected < self.views.len() {
-                        let new_selected = self.layout.get_next_index(self.views.len(), self.selected, 0, -1, self.layout_direction);
-                        self.views.swap(self.selected, new_selected);
-                        self.selected = new_selected;
-                    }
-                },
-                Scancode::K => {
-                    if self.selected < self.views.len() {
-                        let new_selected = self.layout.get_next_index(self.views.len(), self.selected, 0, 1, self.layout_direction);
-                        self.views.swap(self.selected, new_selected);
-                        self.selected = new_selected;
-                    }
+        // the image may be shown by another view with another filter
+        let mut view = View::new(id, image, enable_history);
+        let filter_method = view.filter_method;
+        if let Err(err) = view.set_filter_menthod(filter_method) {
+            self.notifications.error(err.to_string());
+        }
+
+        let index = group::view_range(&self.groups, group).end;
+        self.views.insert(index, view);
+        self.groups[group].view_count += 1;
+        if group < self.group {
+            self.selected += 1;
+        }
+
+        self.update_texture_budget();
+        index
+    }
+
+    /// Indices of the views of the group which is shown.
+    fn shown_views(&self) -> std::ops::Range<usize> {
+        group::view_range(&self.groups, self.group)
+    }
+
+    fn has_selected(&self) -> bool {
+        self.shown_views().contains(&self.selected)
+    }
+
+    /// Limits the estimated memory of all textures, see `TextureBudget`.
+    pub fn set_texture_budget(&mut self, bytes: usize) {
+        self.texture_budget = TextureBudget::new(bytes);
+    }
+
+    fn update_texture_budget(&mut self) {
+        for err in self.texture_budget.update(&self.views, self.shown_views()) {
+            self.notifications.error(err.to_string());
+        }
+    }
+
+    /// Reloads a view from `data` if given, from disk otherwise. With history enabled the
+    /// current content stays in a frozen view and the new content is shown in a new one.
+    fn reload_view(&mut self, view_index: usize, data: Option<&[u8]>) {
+        let view = &mut self.views[view_index];
+        if view.history_enabled {
+            let path = view.image.path.clone();
+            let image = match data {
+                Some(data) => Image::from_memory(&path, data),
+                None => self.image_cache.load(&path),
+            };
+            match image {
+                Ok(image) => {
+                    self.views[view_index].freeze();
+                    self.views[view_index].error = None;
+                    let group = group::group_of(&self.groups, view_index).unwrap_or(self.group);
+                    self.push_view_to_group(group, image, true);
                 },
-                Scancode::J => {
-                    if self.selected < self.views.len() {
-                        let new_selected = self.layout.get_next_index(self.views.len(), self.selected, -1, 0, self.layout_direction);
-                        self.views.swap(self.selected, new_selected);
-                        self.selected = new_selected;
-                    }
+                Err(err) => {
+                    self.views[view_index].error = Some(err.to_string());
+                    self.notifications.error(err.to_string());
                 },
-                Scancode::L => {
-                    if self.selected < self.views.len() {
-                        let new_selected = self.layout.get_next_index(self.views.len(), self.selected, 1, 0, self.layout_direction);
-                        self.views.swap(self.selected, new_selected);
-                        self.selected = new_selected;
+            }
+        } else if let Err(err) = self.reload_in_place(view_index, data) {
+            self.notifications.error(err.to_string());
+        }
+    }
+
+    /// Replaces the content of a view without keeping the current one. An image also shown by other
+    /// views, e.g. a frozen one, isn't changed, the view gets a new image instead.
+    fn reload_in_place(&mut self, view_index: usize, data: Option<&[u8]>) -> Result<(), RimError> {
+        let view = &mut self.views[view_index];
+        let shared = Arc::strong_count(&view.image) > 1 || view.image.is_frozen();
+        if view.frozen || !shared || (view.image.in_memory && data.is_none()) {
+            return match data {
+                Some(data) => view.reload_from_memory(data),
+                None => view.reload(),
+            };
+        }
+
+        let path = view.image.path.clone();
+        let image = match data {
+            Some(data) => Image::from_memory(&path, data),
+            None => self.image_cache.load(&path),
+        };
+
+        let view = &mut self.views[view_index];
+        match image {
+            Ok(image) => {
+                view.image = image;
+                view.error = None;
+                let filter_method = view.filter_method;
+                view.set_filter_menthod(filter_method)
+            },
+            Err(err) => view.track_error(Err(err)),
+        }
+    }
+
+    /// Opens a single image, or all images in a directory. Images in a directory which fail to
+    /// load are reported to the notification list, so the others can still be opened.
+    pub fn open_path(&mut self, path: &Path) -> Result<(), RimError> {
+        if let Some(url) = path.to_str().filter(|path| is_url(path)) {
+            self.open_url(url);
+            return Ok(());
+        }
+
+        if is_stdin(path) {
+            match self.stream_format {
+                Some(format) => self.open_stream(path, format),
+                None => {
+                    let mut data = Vec::new();
+                    if let Err(err) = std::io::stdin().read_to_end(&mut data) {
+                        return Err(RimError::io(path, err));
                     }
+                    self.open_image_from_memory(path, &data, false)?;
                 },
+            }
+            return Ok(());
+        }
 
-                //
-                _ => return false,
+        let path = get_absolute_path(path)?;
+        if is_fifo(&path) {
+            self.open_stream(&path, self.stream_format.unwrap_or(FrameFormat::Png));
+        } else if Path::is_file(&path) {
+            self.open_image(&path, false)?;
+        } else if Path::is_dir(&path) {
+            let expanded = expand_paths(&[path], &PathOptions::default());
+            for msg in expanded.errors.into_iter() {
+                self.notifications.warning(msg);
+            }
+            if expanded.skipped > 0 {
+                self.notifications.info(format!("Skipped {} files which are not images", expanded.skipped));
+            }
+            for path in expanded.paths.into_iter() {
+                self.open_image_in_background(path);
             }
         } else {
-            match scancode {
-                // reload
-                Scancode::F5 => if self.selected < self.views.len() {
-                    match self.views[self.selected].reload() {
-                        Err(msg) => self.error_msg = Some(msg),
-                        Ok(_) => {},
+            return Err(RimError::UnsupportedFormat { path });
+        }
+
+        Ok(())
+    }
+
+    /// Index of the live view of `path` in `group`. Other groups may show the same image in views of their own.
+    fn find_image_by_path(&self, group: usize, path: &Path) -> Option<usize> {
+        group::view_range(&self.groups, group)
+            .find(|&index| !self.views[index].is_frozen() && self.views[index].image.path == path)
+    }
+
+    /// Indices of the live views of `path` in all groups, e.g. to reload them when the file changes.
+    fn live_views_by_path(&self, path: &Path) -> Vec<usize> {
+        (0 .. self.views.len())
+            .filter(|&index| !self.views[index].is_frozen() && self.views[index].image.path == path)
+            .collect()
+    }
+
+    /// Reloads all live views of `path`, opens it in the shown group if there are none.
+    fn reload_path(&mut self, path: &Path, data: Option<&[u8]>) {
+        let views = self.live_views_by_path(path);
+        if views.is_empty() {
+            let result = match data {
+                Some(data) => self.open_image_from_memory(path, data, false),
+                None => self.open_image(path, false),
+            };
+            if let Err(err) = result {
+                self.notifications.error(err.to_string());
+            }
+        }
+
+        // reloading with history adds a view at the end of its group, which moves the views after it
+        for view_index in views.into_iter().rev() {
+            self.reload_view(view_index, data);
+        }
+    }
+
+    /// Sets the file used by the save and load session commands.
+    pub fn set_session_path(&mut self, path: PathBuf) {
+        self.session_path = Some(path);
+    }
+
+    fn get_session_path(&self) -> Option<PathBuf> {
+        match &self.session_path {
+            Some(path) => Some(path.clone()),
+            None => Session::default_path(),
+        }
+    }
+
+    pub fn save_session(&self, path: &Path) -> Result<(), String> {
+        let (width, height) = self.window_size();
+
+        // frozen views are snapshots of past file contents and in memory images have no file,
+        // neither can be restored from disk
+        let restorable = |view: &View| !view.is_frozen() && !view.image.in_memory;
+
+        let groups = self.groups.iter().enumerate()
+            .map(|(index, group)| {
+                let views = &self.views[group::view_range(&self.groups, index)];
+                let selected = if index == self.group { self.selected - self.shown_views().start } else { group.selected };
+                let selected = views.iter().take(selected).filter(|view| restorable(view)).count();
+
+                // the panes of views which aren't stored would be left empty
+                let mut split_layout = group.split_layout.clone();
+                split_layout.fit(views.len(), group.layout_direction);
+                for (index, view) in views.iter().enumerate().rev() {
+                    if !restorable(view) {
+                        split_layout.remove(index);
                     }
-                },
+                }
 
-                // switch selection
-                // Scancode::Tab, keymod: Mod::LSHIFTMOD => {
-                //     if self.selected < self.views.len() {
-                //         self.views[self.selected].selected = false;
-                //         self.selected =(self.selected + self.views.len() - 1) % self.views.len();
-                //         self.views[self.selected].selected = true;
-                //     }
-                // },
-                // Scancode::Tab => {
-                //     if self.selected < self.views.len() {
-                //         self.views[self.selected].selected = false;
-                //         self.selected = (self.selected + 1) % self.views.len();
-                //         self.views[self.selected].selected = true;
-                //     }
-                // },
-
-                Scancode::I => if self.selected < self.views.len() {
-                    self.views[self.selected].selected = false;
-                    self.selected = self.layout.get_next_index(self.views.len(), self.selected, 0, -1, self.layout_direction);
-                    self.views[self.selected].selected = true;
-                },
-                Scancode::K => if self.selected < self.views.len() {
-                    self.views[self.selected].selected = false;
-                    self.selected = self.layout.get_next_index(self.views.len(), self.selected, 0, 1, self.layout_direction);
-                    self.views[self.selected].selected = true;
-                },
-                Scancode::J => if self.selected < self.views.len() {
-                    self.views[self.selected].selected = false;
-                    self.selected = self.layout.get_next_index(self.views.len(), self.selected, -1, 0, self.layout_direction);
-                    self.views[self.selected].selected = true;
+                GroupState {
+                    name        : group.name.clone(),
+                    selected,
+                    layout      : LayoutState {
+                        kind     : group.layout_kind,
+                        direction: if group.auto_layout_dir { None } else { Some(group.layout_direction) },
+                        split    : Some(split_layout.tree().clone()),
+                    },
+                    views       : views.iter()
+                        .filter(|view| restorable(view))
+                        .map(|view| ViewState {
+                            path            : view.image.path.clone(),
+                            zoom            : view.zoom,
+                            rect_pos        : view.rect_pos.into(),
+                            filter_method   : view.filter_method,
+                            history         : view.history_enabled,
+                        })
+                        .collect(),
+                }
+            })
+            .collect();
+
+        Session::new(self.group, WindowState { width, height }, groups).save(path)
+    }
+
+    /// Replaces all open images with the ones stored in the session file.
+    pub fn load_session(&mut self, path: &Path) -> Result<(), String> {
+        let session = Session::load(path)?;
+
+        self.views.clear();
+        self.remote_handles.clear();
+        self.streams.clear();
+        self.image_cache.cancel_background();
+        self.pending_images.clear();
+        self.loaded_images.clear();
+        self.groups.clear();
+        self.selected = 0;
+        self.dragged_divider = None;
+
+        if !self.maximized {
+            self.set_window_size(session.window.width, session.window.height);
+        }
+
+        for state in session.groups.into_iter() {
+            let mut group = ViewGroup::new(state.name);
+            group.layout_kind = state.layout.kind;
+            group.split_layout = state.layout.split.map(SplitLayout::from_tree).unwrap_or_default();
+            match state.layout.direction {
+                Some(direction) => {
+                    group.auto_layout_dir = false;
+                    group.layout_direction = direction;
                 },
-                Scancode::L => if self.selected < self.views.len() {
-                    self.views[self.selected].selected = false;
-                    self.selected = self.layout.get_next_index(self.views.len(), self.selected, 1, 0, self.layout_direction);
-                    self.views[self.selected].selected = true;
+                None => group.auto_layout_dir = true,
+            }
+            group.selected = state.selected;
+            self.groups.push(group);
+
+            // views are opened into the group which is shown
+            self.group = self.groups.len() - 1;
+            for state in state.views.iter() {
+                if let Some(url) = state.path.to_str().filter(|path| is_url(path)) {
+                    self.open_url(url);
+                    continue;
+                }
+
+                match self.open_image(&state.path, state.history) {
+                    Ok(index) => {
+                        let view = &mut self.views[index];
+                        view.zoom = state.zoom;
+                        view.rect_pos = Vec2::from(state.rect_pos);
+                        if let Err(err) = view.set_filter_menthod(state.filter_method) {
+                            self.notifications.error(err.to_string());
+                        }
+                    },
+                    Err(err) => self.notifications.error(err.to_string()),
+                }
+            }
+        }
+
+        if self.groups.is_empty() {
+            self.groups.push(ViewGroup::new(group::default_name(&[])));
+        }
+        self.group = std::cmp::min(session.group, self.groups.len() - 1);
+        self.show_group_selection();
+
+        Ok(())
+    }
+
+    fn save_session_to_default(&mut self) {
+        match self.get_session_path() {
+            Some(path) => match self.save_session(&path) {
+                Ok(_) => self.notifications.info(format!("Saved session to {:?}", path)),
+                Err(msg) => self.notifications.error(msg),
+            },
+            None => self.notifications.error("No session file, use --session <file>".to_owned()),
+        }
+    }
+
+    fn load_session_from_default(&mut self) {
+        match self.get_session_path() {
+            Some(path) => match self.load_session(&path) {
+                Ok(_) => {},
+                Err(msg) => self.notifications.error(msg),
+            },
+            None => self.notifications.error("No session file, use --session <file>".to_owned()),
+        }
+    }
+
+    /// Starts listening for remote commands, see `ipc` for the protocol.
+    pub fn start_ipc_server(&mut self, path: &Path) -> Result<(), String> {
+        match IpcServer::start(path) {
+            Ok(server) => {
+                self.ipc_server = Some(server);
+                Ok(())
+            },
+            Err(err) => Err(format!("Failed to listen on {:?}: {}", path, err)),
+        }
+    }
+
+    /// Index of the view a remote command refers to, the selected one if no path is given.
+    /// Views in the shown group are preferred, then the first one in any group.
+    fn find_view_for_command(&mut self, path: &Option<PathBuf>) -> Option<usize> {
+        let path = match path {
+            Some(path) if path.to_str().is_some_and(is_url) => Some(path.clone()),
+            Some(path) => match path.canonicalize() {
+                Ok(path) => Some(path),
+                Err(_) => return None,
+            },
+            None => None,
+        };
+
+        match path {
+            Some(path) => self.find_image_by_path(self.group, &path)
+                .or_else(|| self.live_views_by_path(&path).first().copied()),
+            None if self.has_selected() => Some(self.selected),
+            None => None,
+        }
+    }
+
+    /// Runs a remote command, the error is also sent back to the client.
+    fn handle_command(&mut self, command: Command) -> Result<(), String> {
+        match command {
+            Command::Open(path) => {
+                let result = if path.is_file() {
+                    self.open_image(&path, false).map(|index| self.select(index))
+                } else {
+                    self.open_path(&path)
+                };
+                result.map_err(|err| err.to_string())
+            },
+            Command::Raise => {
+                if let Platform::Window(window) = &mut self.platform {
+                    window.window.restore();
+                    window.window.raise();
+                }
+                Ok(())
+            },
+            Command::Close(path) => match self.find_view_for_command(&path) {
+                Some(index) => {
+                    self.close_view(index);
+                    Ok(())
                 },
+                None => Err(not_open_message(&path)),
+            },
+            Command::Reload(path) => match self.find_view_for_command(&path) {
+                Some(index) => self.reload_in_place(index, None).map_err(|err| err.to_string()),
+                None => Err(not_open_message(&path)),
+            },
+            Command::Select(path) => {
+                let path = Some(path);
+                match self.find_view_for_command(&path) {
+                    Some(index) => {
+                        self.select(index);
+                        Ok(())
+                    },
+                    None => Err(not_open_message(&path)),
+                }
+            },
+        }
+    }
+
+    /// Selects the view at `index`, showing its group if it's in another one.
+    fn select(&mut self, index: usize) {
+        if let Some(group) = group::group_of(&self.groups, index) {
+            self.show_group(group);
+        }
+        if self.has_selected() {
+            self.views[self.selected].selected = false;
+        }
+        self.selected = index;
+        self.views[self.selected].selected = true;
+    }
+
+    fn close_view(&mut self, index: usize) {
+        let group = match group::group_of(&self.groups, index) {
+            Some(group) => group,
+            None => return,
+        };
+        let start = group::view_range(&self.groups, group).start;
+        let view = self.views.remove(index);
+        self.groups[group].view_count -= 1;
+        self.groups[group].split_layout.remove(index - start);
+
+        // stop polling a URL once its last live view is closed
+        if !view.is_frozen() && self.live_views_by_path(&view.image.path).is_empty() {
+            if let Some(url) = view.image.path.to_str() {
+                self.remote_handles.remove(url);
+            }
+        }
 
-                //
-                _ => return false,
+        if group != self.group {
+            if index < self.selected {
+                self.selected -= 1;
+            }
+            let selected = &mut self.groups[group].selected;
+            if index - start < *selected {
+                *selected -= 1;
             }
+            if self.groups[group].view_count == 0 {
+                self.groups.remove(group);
+                if group < self.group {
+                    self.group -= 1;
+                }
+            }
+            return;
         }
 
-        return true;
+        let shown = self.shown_views();
+        if !shown.is_empty() {
+            if index < self.selected {
+                self.selected -= 1;
+            }
+            if self.selected >= shown.end {
+                self.selected = shown.start;
+            }
+            self.views[self.selected].selected = true;
+        } else {
+            self.selected = shown.start;
+        }
     }
 
-    pub fn run(&mut self) {
-        if self.views.len() > 0 {
-            self.views[0].selected = true;
+    /// Shows the views of another group, with the view selected that was selected when it was shown last.
+    /// The group shown before is removed if it's empty.
+    fn show_group(&mut self, group: usize) {
+        if group == self.group || group >= self.groups.len() {
+            return;
         }
 
-        let mut event_pump = self.sdl.event_pump().unwrap();
-        'main: loop {
-            let mut open_file_open_dialog = false;
-            let mut close_view = false;
-            let mut mouse_moved = false;
-            let mut right_clicked = false;
+        if self.has_selected() {
+            self.views[self.selected].selected = false;
+            self.groups[self.group].selected = self.selected - self.shown_views().start;
+        }
 
-            
-            for event in event_pump.poll_iter() {
-                if self.open_file_dialog.is_open() {
-                    use sdl2::event::Event;
+        let previous = self.group;
+        self.group = group;
+        if self.groups[previous].view_count == 0 {
+            self.groups.remove(previous);
+            if self.group > previous {
+                self.group -= 1;
+            }
+        }
 
-                    if let Event::MouseMotion { .. } = event {
-                        mouse_moved = true;
-                    }
-                    if let Event::MouseWheel { .. } = event {
-                        mouse_moved = true;
-                    }
+        self.dragged_divider = None;
+        self.show_group_selection();
+    }
 
-                    match event {
-                        // quit
-                        Event::Quit { .. } => break 'main,
-                        Event::KeyDown { scancode: Some(sdl2::keyboard::Scancode::P), keymod: sdl2::keyboard::Mod::LCTRLMOD, .. } |
-                        Event::KeyDown { scancode: Some(sdl2::keyboard::Scancode::P), keymod: sdl2::keyboard::Mod::RCTRLMOD, .. } => {
-                            break 'main;
-                        },
-
-                        //
-                        _ => {
-                            self.imgui_sdl2.handle_event(&mut self.imgui, &event);
-                        }
-                    }
-                } else if self.error_msg.is_some() {
-                    use sdl2::event::Event;
-
-                    match event {
-                        // quit
-                        Event::Quit { .. } => break 'main,
-                        Event::KeyDown { scancode: Some(sdl2::keyboard::Scancode::P), keymod: sdl2::keyboard::Mod::LCTRLMOD, .. } |
-                        Event::KeyDown { scancode: Some(sdl2::keyboard::Scancode::P), keymod: sdl2::keyboard::Mod::RCTRLMOD, .. } => {
-                            break 'main;
-                        },
-
-                        Event::KeyDown { scancode: Some(sdl2::keyboard::Scancode::Space), .. } |
-                        Event::KeyDown { scancode: Some(sdl2::keyboard::Scancode::Return), .. } |
-                        Event::KeyDown { scancode: Some(sdl2::keyboard::Scancode::Escape), .. } => {
-                            self.error_msg = None;
-                        },
-
-                        //
-                        _ => {
-                            self.imgui_sdl2.handle_event(&mut self.imgui, &event);
-                        }
-                    }
+    /// Selects the view of the group which is shown that the group remembers as selected.
+    fn show_group_selection(&mut self) {
+        let shown = self.shown_views();
+        if shown.is_empty() {
+            self.selected = shown.start;
+        } else {
+            self.selected = shown.start + std::cmp::min(self.groups[self.group].selected, shown.len() - 1);
+            self.views[self.selected].selected = true;
+        }
+    }
+
+    /// Shows the group `offset` groups away, wrapping around at the ends.
+    fn show_next_group(&mut self, offset: i32) {
+        let count = self.groups.len() as i32;
+        let group = ((self.group as i32 + offset) % count + count) % count;
+        self.show_group(group as usize);
+    }
+
+    /// Adds an empty group after the one shown and shows it.
+    fn new_group(&mut self) {
+        let name = group::default_name(&self.groups);
+        self.groups.insert(self.group + 1, ViewGroup::new(name));
+        self.show_group(self.group + 1);
+    }
+
+    /// Moves the selected view to the end of the group `offset` groups away. Moving it past the first
+    /// or last group adds a new one. The selection stays in the group which is shown.
+    fn move_to_group(&mut self, offset: i32) {
+        if !self.has_selected() {
+            return;
+        }
+
+        let target = self.group as i32 + offset;
+        let target = if target < 0 {
+            self.groups.insert(0, ViewGroup::new(group::default_name(&self.groups)));
+            self.group += 1;
+            0
+        } else if target as usize >= self.groups.len() {
+            self.groups.push(ViewGroup::new(group::default_name(&self.groups)));
+            self.groups.len() - 1
+        } else {
+            target as usize
+        };
+
+        let local = self.selected - self.shown_views().start;
+        let mut view = self.views.remove(self.selected);
+        view.selected = false;
+        self.groups[self.group].view_count -= 1;
+        self.groups[self.group].split_layout.remove(local);
+
+        let index = group::view_range(&self.groups, target).end;
+        self.views.insert(index, view);
+        self.groups[target].view_count += 1;
+        self.groups[target].selected = self.groups[target].view_count - 1;
+        self.notifications.info(format!("Moved image to {}", self.groups[target].name));
+
+        self.groups[self.group].selected = local;
+        self.show_group_selection();
+    }
+
+    fn open_file_open_dialog(&mut self) {
+        let local = |view: &View| !view.image.in_memory && !view.image.path.to_str().is_some_and(is_url);
+        let path = if self.has_selected() && local(&self.views[self.selected]) {
+            let sel_path: &Path = &self.views[self.selected].image.path;
+            match sel_path.parent() {
+                Some(parent) => parent.to_string_lossy().into_owned(),
+                None => sel_path.to_string_lossy().into_owned(),
+            }
+        } else {
+            match std::env::current_dir() {
+                Ok(dir) => dir.to_string_lossy().into_owned(),
+                Err(_) => ".".to_owned(),
+            }
+        };
+        self.open_file_dialog.open(path);
+    }
+
+    /// Index of the view next to the selected one in the layout of the group shown.
+    fn get_next_index(&self, x_off: i32, y_off: i32) -> usize {
+        let (width, height) = self.view_area_size();
+        let shown = self.shown_views();
+        let group = &self.groups[self.group];
+        let layout = group.current_layout();
+        let selected = self.selected - shown.start;
+        let cells = layout::cells(layout, &self.views[shown.clone()], selected, width, height, group.layout_direction);
+        shown.start + layout.get_next_index(&cells, selected, x_off, y_off, self.edge_behavior)
+    }
+
+    /// Size of the area the views are laid out in, below the tabs when there are several groups.
+    fn view_area_size(&self) -> (i32, i32) {
+        let (width, height) = self.window_size();
+        (width as i32, height as i32 - self.tab_bar_height())
+    }
+
+    fn tab_bar_height(&self) -> i32 {
+        if self.groups.len() > 1 { TAB_BAR_HEIGHT } else { 0 }
+    }
+
+    fn select_next(&mut self, x_off: i32, y_off: i32) {
+        if self.has_selected() {
+            self.views[self.selected].selected = false;
+            self.selected = self.get_next_index(x_off, y_off);
+            self.views[self.selected].selected = true;
+        }
+    }
+
+    fn move_selected(&mut self, x_off: i32, y_off: i32) {
+        if self.has_selected() {
+            let new_selected = self.get_next_index(x_off, y_off);
+            self.views.swap(self.selected, new_selected);
+            self.selected = new_selected;
+        }
+    }
+
+    fn set_layout(&mut self, kind: LayoutKind) {
+        self.groups[self.group].layout_kind = kind;
+    }
+
+    /// Applies `edit` to the split layout with the selected view and switches to the split layout to show the result.
+    fn edit_split_layout(&mut self, edit: impl FnOnce(&mut SplitLayout, usize) -> bool) {
+        if self.has_selected() {
+            let shown = self.shown_views();
+            let group = &mut self.groups[self.group];
+            group.split_layout.fit(shown.len(), group.layout_direction);
+            if edit(&mut group.split_layout, self.selected - shown.start) {
+                group.layout_kind = LayoutKind::Split;
+            }
+        }
+    }
+
+    fn set_filter_method(&mut self, filter_method: FilterMethod) {
+        if self.has_selected() {
+            if let Err(err) = self.views[self.selected].set_filter_menthod(filter_method) {
+                self.notifications.error(err.to_string());
+            }
+        }
+    }
+
+    fn handle_action(&mut self, action: Action, close_view: &mut bool, open_file_open_dialog: &mut bool) {
+        match action {
+            // handled by the main loop
+            Action::Quit => {},
+
+            Action::Reload => if self.has_selected() {
+                match self.reload_in_place(self.selected, None) {
+                    Err(err) => self.notifications.error(err.to_string()),
+                    Ok(_) => {},
+                }
+            },
+
+            // filter method
+            Action::FilterLinear => self.set_filter_method(FilterMethod::Linear),
+            Action::FilterNearest => self.set_filter_method(FilterMethod::Nearest),
+            Action::FilterTrilinear => self.set_filter_method(FilterMethod::Trilinear),
+            Action::FilterLanczos => self.set_filter_method(FilterMethod::Lanczos),
+
+            // layout direction
+            Action::LayoutHorizontal => {
+                self.groups[self.group].auto_layout_dir = false;
+                self.groups[self.group].layout_direction = LayoutDirection::Horizontal;
+            },
+            Action::LayoutVertical => {
+                self.groups[self.group].auto_layout_dir = false;
+                self.groups[self.group].layout_direction = LayoutDirection::Vertical;
+            },
+            Action::LayoutAuto => self.groups[self.group].auto_layout_dir = true,
+
+            // layout
+            Action::LayoutGrid => self.set_layout(LayoutKind::Grid),
+            Action::LayoutRows => self.set_layout(LayoutKind::Rows),
+            Action::LayoutMasonry => self.set_layout(LayoutKind::Masonry),
+            Action::LayoutFocus => self.set_layout(LayoutKind::Focus),
+            Action::LayoutSplit => self.set_layout(LayoutKind::Split),
+
+            // split layout
+            Action::SplitHorizontal => self.edit_split_layout(|split, index| split.split(index, LayoutDirection::Horizontal)),
+            Action::SplitVertical => self.edit_split_layout(|split, index| split.split(index, LayoutDirection::Vertical)),
+            Action::MergePanes => self.edit_split_layout(|split, index| split.merge(index)),
+            Action::GrowPane => self.edit_split_layout(|split, index| split.resize(index, PANE_RESIZE_STEP)),
+            Action::ShrinkPane => self.edit_split_layout(|split, index| split.resize(index, -PANE_RESIZE_STEP)),
+
+            // groups
+            Action::NextGroup => self.show_next_group(1),
+            Action::PreviousGroup => self.show_next_group(-1),
+            Action::NewGroup => self.new_group(),
+            Action::MoveToNextGroup => self.move_to_group(1),
+            Action::MoveToPreviousGroup => self.move_to_group(-1),
+
+            // close selected
+            Action::CloseView => *close_view = true,
+
+            // open new
+            Action::OpenFile => *open_file_open_dialog = true,
+
+            // maximize
+            Action::ToggleFullscreen => if let Platform::Window(window) = &mut self.platform {
+                if self.maximized {
+                    window.window.set_fullscreen(sdl2::video::FullscreenType::Off).unwrap_or(());
                 } else {
-                    use sdl2::event::Event;
-                    use sdl2::keyboard::*;
-                    use sdl2::mouse::*;
+                    window.window.set_fullscreen(sdl2::video::FullscreenType::True).unwrap_or(());
+                }
+                self.maximized = window.window.fullscreen_state() == sdl2::video::FullscreenType::True;
+            },
 
-                    if let Event::MouseButtonUp { mouse_btn: MouseButton::Right, .. } = event {
-                        right_clicked = true;
-                    }
+            Action::ToggleHistory => if self.has_selected() {
+                let view = &mut self.views[self.selected];
+                view.history_enabled = !view.history_enabled;
+            },
 
-                    match event {
-                        // quit
-                        Event::Quit { .. } => break 'main,
-                        Event::KeyDown { scancode: Some(Scancode::P), keymod: Mod::LCTRLMOD, .. } |
-                        Event::KeyDown { scancode: Some(Scancode::P), keymod: Mod::RCTRLMOD, .. } => {
-                            break 'main;
-                        },
-
-                        // toggle titlebar
-                        Event::KeyDown { scancode: Some(Scancode::LAlt), repeat: false, .. } => {
-                            let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis();
-                            if now - self.show_titlebar_timer < 200 {
-                                self.show_titlebars = !self.show_titlebars;
-                            }
-                            self.show_titlebar_timer = now;
-                        },
-
-                        // all other events
-                        Event::KeyDown { scancode: Some(scancode), keymod, .. } => {
-                            if !self.handle_event(scancode, keymod, &mut close_view, &mut open_file_open_dialog) {
-                            }
-                            self.imgui_sdl2.handle_event(&mut self.imgui, &event);
-                        },
-                        _ => self.imgui_sdl2.handle_event(&mut self.imgui, &event),
-                    }
+            Action::ToggleTitlebars => self.show_titlebars = !self.show_titlebars,
+
+            Action::CommandPalette => self.command_palette.open(),
+
+            Action::SaveSession => self.save_session_to_default(),
+            Action::LoadSession => self.load_session_from_default(),
+
+            Action::DismissNotifications => self.notifications.clear(),
+            Action::ToggleNotificationLog => self.notifications.toggle_history(),
+            Action::ToggleLog => match &mut self.log_viewer {
+                Some(log_viewer) => log_viewer.toggle(),
+                None => self.notifications.warning("Logging is not set up".to_owned()),
+            },
+            Action::ToggleDebugOverlay => self.debug_overlay.toggle(),
+
+            // move selection
+            Action::SelectUp => self.select_next(0, -1),
+            Action::SelectDown => self.select_next(0, 1),
+            Action::SelectLeft => self.select_next(-1, 0),
+            Action::SelectRight => self.select_next(1, 0),
+
+            // move selected
+            Action::MoveUp => self.move_selected(0, -1),
+            Action::MoveDown => self.move_selected(0, 1),
+            Action::MoveLeft => self.move_selected(-1, 0),
+            Action::MoveRight => self.move_selected(1, 0),
+
+            Action::ResetZoom => if self.has_selected() {
+                self.views[self.selected].reset_zoom();
+            },
+
+            // pan and zoom are handled by the selected view while the keys are held
+            Action::PanUp | Action::PanDown | Action::PanLeft | Action::PanRight |
+            Action::ZoomIn | Action::ZoomOut => {},
+        }
+    }
+
+    pub fn run(&mut self) {
+        if self.has_selected() {
+            self.views[self.selected].selected = true;
+        }
+
+        let mut event_pump = match &self.platform {
+            Platform::Window(window) => window.sdl.event_pump().unwrap(),
+            Platform::Headless(_) => return,
+        };
+        let mut frames_to_draw = FRAMES_AFTER_CHANGE;
+        loop {
+            // keep drawing while keys or buttons are held, e.g. for panning, otherwise wait until something happens,
+            // background threads push an event when they have something, see `wake`
+            let held = event_pump.keyboard_state().pressed_scancodes().any(|scancode| !is_modifier(scancode))
+                || event_pump.mouse_state().pressed_mouse_buttons().next().is_some();
+            let animating = self.continuous_redraw || held;
+
+            let mut events = Vec::new();
+            if !animating && frames_to_draw == 0 {
+                if wake::is_registered() {
+                    events.push(event_pump.wait_event());
+                } else {
+                    events.extend(event_pump.wait_event_timeout(IDLE_WAIT));
                 }
             }
+            events.extend(event_pump.poll_iter());
+            let mut changed = !events.is_empty();
+
+            let mut requests = FrameRequests::default();
+            if !self.handle_events(events, &mut requests) {
+                break;
+            }
+            changed |= self.handle_updates();
+
+            if changed {
+                frames_to_draw = FRAMES_AFTER_CHANGE;
+            } else if self.notifications.has_toasts() {
+                // toasts have to disappear once they expire
+                frames_to_draw = std::cmp::max(frames_to_draw, 1);
+            }
+            if !animating && frames_to_draw == 0 {
+                continue;
+            }
+            frames_to_draw = frames_to_draw.saturating_sub(1);
+            let texture_stats_before = texture_stats();
+
+            if let Platform::Window(window) = &mut self.platform {
+                window.imgui_sdl2.prepare_frame(
+                    self.imgui.io_mut(),
+                    &window.window,
+                    &event_pump.mouse_state(),
+                );
+            }
+            if !self.draw_frame(requests) {
+                break;
+            }
+
+            // textures are scaled and tiles uploaded a few per frame, draw until all are done
+            if texture_stats() != texture_stats_before {
+                frames_to_draw = std::cmp::max(frames_to_draw, 1);
+            }
+        }
+    }
+
+    /// Handles `events` like `run` does and draws a frame, returns it or `None` once the events quit the app.
+    /// Only for apps created with `headless`. Every step is one frame of a fixed length, so the frames only
+    /// depend on the events, e.g. holding a pan key for ten steps always pans the same distance.
+    pub fn step(&mut self, events: Vec<sdl2::event::Event>) -> Option<image::RgbaImage> {
+        if self.has_selected() {
+            self.views[self.selected].selected = true;
+        }
+
+        let mut requests = FrameRequests::default();
+        if !self.handle_events(events, &mut requests) {
+            return None;
+        }
+        self.handle_updates();
+
+        if let Platform::Headless(headless) = &mut self.platform {
+            headless.prepare_frame(self.imgui.io_mut());
+        }
+        if !self.draw_frame(requests) {
+            return None;
+        }
+        Some(renderer::with(|renderer| renderer.read_frame()))
+    }
+
+    /// Passes input events on to imgui, with the headless platform resize events change the window size.
+    fn platform_event(&mut self, event: &sdl2::event::Event) {
+        match &mut self.platform {
+            Platform::Window(window) => window.imgui_sdl2.handle_event(&mut self.imgui, event),
+            Platform::Headless(headless) => headless.handle_event(&mut self.imgui, event),
+        }
+    }
 
-            let mut view_to_reload = None;
+    /// Handles shortcuts and passes the events on to imgui, returns false if one of them quits the app.
+    fn handle_events(&mut self, events: Vec<sdl2::event::Event>, requests: &mut FrameRequests) -> bool {
+        for event in events.into_iter() {
+            if self.open_file_dialog.is_open() || self.command_palette.is_open() {
+                use sdl2::event::Event;
+
+                if let Event::MouseMotion { .. } = event {
+                    requests.mouse_moved = true;
+                }
+                if let Event::MouseWheel { .. } = event {
+                    requests.mouse_moved = true;
+                }
 
-            while let Ok(event) = self.dir_watcher_recv.try_recv() {
-                println!("{:?}", event);
                 match event {
-                    notify::DebouncedEvent::NoticeWrite(_) => {},
-                    notify::DebouncedEvent::NoticeRemove(_) => {},
-                    notify::DebouncedEvent::Create(_) => {},
-                    notify::DebouncedEvent::Write(path) => {
-                        view_to_reload = self.find_image_by_path(&path);
+                    // quit
+                    Event::Quit { .. } => return false,
+                    Event::KeyDown { scancode: Some(scancode), keymod, .. } if self.keymap.action_for(scancode, keymod) == Some(Action::Quit) => {
+                        return false;
                     },
-                    notify::DebouncedEvent::Chmod(_) => {},
-                    notify::DebouncedEvent::Remove(_) => {},
-                    notify::DebouncedEvent::Rename(_, _) => {},
-                    notify::DebouncedEvent::Rescan => {},
-                    notify::DebouncedEvent::Error(_, _) => {},
+
+                    //
+                    _ => {
+                        self.platform_event(&event);
+                    }
                 }
-            }
+            } else {
+                use sdl2::event::Event;
+                use sdl2::keyboard::*;
+                use sdl2::mouse::*;
 
-            if let Some(view_index) = view_to_reload {
-                let view = &mut self.views[view_index];
-                let history_enabled = view.history_enabled;
-                if history_enabled {
-                    view.freeze();
-                    let path = view.image.path.clone();
-                    self.open_image(&path, history_enabled).unwrap();
-                } else {
-                    match view.reload() {
-                        Ok(_) => {},
-                        Err(msg) => {
-                            self.error_msg = Some(msg);
+                if let Event::MouseButtonUp { mouse_btn: MouseButton::Right, .. } = event {
+                    requests.right_clicked = true;
+                }
+
+                match event {
+                    // quit
+                    Event::Quit { .. } => return false,
+
+                    // toggle titlebar
+                    Event::KeyDown { scancode: Some(Scancode::LAlt), repeat: false, .. } => {
+                        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis();
+                        if now - self.show_titlebar_timer < 200 {
+                            self.show_titlebars = !self.show_titlebars;
                         }
-                    }
+                        self.show_titlebar_timer = now;
+                    },
+
+                    // all other events
+                    Event::KeyDown { scancode: Some(scancode), keymod, .. } => {
+                        match self.keymap.action_for(scancode, keymod) {
+                            Some(Action::Quit) => return false,
+                            Some(action) if !action.is_continuous() => {
+                                self.handle_action(action, &mut requests.close_view, &mut requests.open_file_open_dialog);
+                            },
+                            _ => {},
+                        }
+                        self.platform_event(&event);
+                    },
+                    _ => self.platform_event(&event),
                 }
             }
+        }
 
-            // auto layout
-            if self.auto_layout_dir {
-                // calc average aspect ratio
+        true
+    }
 
-                let mut aspect = 0.0;
-                let mut count = 0.0;
-                for view in self.views.iter() {
-                    aspect += view.image.width as f32 / view.image.height as f32;
-                    count += 1.0;
-                }
+    /// Handles remote commands, changed files, images decoded in the background, downloads and stream frames.
+    /// Returns whether any of them changed something.
+    fn handle_updates(&mut self) -> bool {
+        let mut changed = false;
+
+        // remote commands
+        while let Some(request) = self.ipc_server.as_ref().and_then(|server| server.try_recv()) {
+            let result = self.handle_command(request.command.clone());
+            if let Err(msg) = &result {
+                self.notifications.error(msg.clone());
+            }
+            request.answer(result);
+            changed = true;
+        }
 
-                let avg = aspect / count;
+        let mut paths_to_reload = Vec::new();
 
-                let window_size = self.window.drawable_size();
-                self.layout_direction = if (window_size.0 as f32 / window_size.1 as f32) > avg {
-                    LayoutDirection::Horizontal
-                } else {
-                    LayoutDirection::Vertical
-                }
+        while let Ok(event) = self.dir_watcher_recv.try_recv() {
+            log::trace!("{:?}", event);
+            match event {
+                notify::DebouncedEvent::NoticeWrite(_) => {},
+                notify::DebouncedEvent::NoticeRemove(_) => {},
+                notify::DebouncedEvent::Create(_) => {},
+                notify::DebouncedEvent::Write(path) => if !paths_to_reload.contains(&path) {
+                    paths_to_reload.push(path);
+                },
+                notify::DebouncedEvent::Chmod(_) => {},
+                notify::DebouncedEvent::Remove(_) => {},
+                notify::DebouncedEvent::Rename(_, _) => {},
+                notify::DebouncedEvent::Rescan => {},
+                notify::DebouncedEvent::Error(_, _) => {},
+            }
+        }
+
+        for path in paths_to_reload.iter() {
+            self.reload_path(path, None);
+            changed = true;
+        }
+
+        // images decoded in the background
+        changed |= self.open_loaded_images();
+
+        // downloads
+        while let Some(event) = self.remote_images.try_recv() {
+            self.handle_remote_event(event);
+            changed = true;
+        }
+
+        // streams
+        for i in 0..self.streams.len() {
+            if let Some(frame) = self.streams[i].latest_frame() {
+                changed = true;
+                let path = self.streams[i].path.clone();
+                self.reload_path(&path, Some(&frame));
+            }
+        }
+
+        changed
+    }
+
+    /// Lays out and draws the views and all other windows, then does what was asked for while drawing.
+    /// The platform must have prepared the frame. Returns false if the app should quit.
+    fn draw_frame(&mut self, requests: FrameRequests) -> bool {
+        let FrameRequests { mut open_file_open_dialog, mut close_view, mouse_moved, right_clicked } = requests;
+        let mut open_command_palette = false;
+        let mut toggle_notification_log = false;
+        let mut save_session = false;
+        let mut load_session = false;
+        let mut view_error = None;
+        let mut reload_selected = false;
+        let mut new_group = false;
+        let mut clicked_group = None;
+
+        let window_size = self.window_size();
+        let drawable_size = self.drawable_size();
+
+        let shown = self.shown_views();
+        let tab_bar_height = self.tab_bar_height();
+        let (area_width, area_height) = self.view_area_size();
+        let group = &mut self.groups[self.group];
+
+        // auto layout
+        if group.auto_layout_dir {
+            // calc average aspect ratio
+
+            let mut aspect = 0.0;
+            let mut count = 0.0;
+            for view in self.views[shown.clone()].iter() {
+                aspect += view.image.width as f32 / view.image.height as f32;
+                count += 1.0;
             }
 
-            self.imgui_sdl2.prepare_frame(
-                self.imgui.io_mut(),
-                &self.window,
-                &event_pump.mouse_state(),
-            );
+            let avg = aspect / count;
 
-            let ui = self.imgui.frame();
-            // ui.show_demo_window(&mut true);
+            group.layout_direction = if (drawable_size.0 as f32 / drawable_size.1 as f32) > avg {
+                LayoutDirection::Horizontal
+            } else {
+                LayoutDirection::Vertical
+            }
+        }
 
+        let ui = self.imgui.frame();
+        // ui.show_demo_window(&mut true);
 
-            let window_size = self.window.size();
+        // the split layout follows opened views even while another layout is used, closed ones are removed right away
+        group.split_layout.fit(shown.len(), group.layout_direction);
 
-            if self.views.len() > 0 {
-                self.layout.layout(&mut self.views, window_size.0 as i32, window_size.1 as i32, self.layout_direction);
+        if !shown.is_empty() {
+            let views = &mut self.views[shown.clone()];
+            layout::apply(group.current_layout(), views, self.selected - shown.start, area_width, area_height, group.layout_direction);
+            for view in views.iter_mut() {
+                view.y += tab_bar_height;
             }
+        }
 
-            // context menu
-            {
-                use imgui::*;
+        for err in self.texture_budget.update(&self.views, shown.clone()) {
+            self.notifications.error(err.to_string());
+        }
+        // context menu
+        {
+            use imgui::*;
 
-                let context_menu_id = im_str!("ContextMenu");
+            let context_menu_id = im_str!("ContextMenu");
+            let mut layout_kind = self.groups[self.group].layout_kind;
+            let mut group_name = ImString::with_capacity(64);
+            group_name.push_str(&self.groups[self.group].name);
 
-                if self.selected < self.views.len() {
-                    let view = &mut self.views[self.selected];
+            if shown.contains(&self.selected) {
+                let view = &mut self.views[self.selected];
 
-                    ui.popup(context_menu_id, || {
-                        ui.text(view.image.path.to_str().unwrap_or(""));
-                        ui.separator();
+                ui.popup(context_menu_id, || {
+                    ui.text(view.image.path.to_str().unwrap_or(""));
+                    ui.separator();
 
-                        // open
-                        if imgui::MenuItem::new(im_str!("Open")).build(&ui) {
-                            open_file_open_dialog = true;
-                        }
+                    // open
+                    if imgui::MenuItem::new(im_str!("Open")).build(&ui) {
+                        open_file_open_dialog = true;
+                    }
 
-                        ui.separator();
+                    if imgui::MenuItem::new(im_str!("Command Palette")).build(&ui) {
+                        open_command_palette = true;
+                    }
 
-                        if imgui::MenuItem::new(im_str!("Close")).build(&ui) {
-                            close_view = true;
-                        }
+                    if imgui::MenuItem::new(im_str!("Notification Log")).build(&ui) {
+                        toggle_notification_log = true;
+                    }
 
-                        // reload from disk
-                        if imgui::MenuItem::new(im_str!("Reload from disk")).build(&ui) {
-                            view.reload().unwrap_or(());
-                        }
+                    ui.separator();
+
+                    if imgui::MenuItem::new(im_str!("Save Session")).build(&ui) {
+                        save_session = true;
+                    }
+
+                    if imgui::MenuItem::new(im_str!("Load Session")).build(&ui) {
+                        load_session = true;
+                    }
+
+                    layout_menu(&ui, &mut layout_kind);
+                    group_menu(&ui, &mut group_name, &mut new_group);
+
+                    ui.separator();
+
+                    if imgui::MenuItem::new(im_str!("Close")).build(&ui) {
+                        close_view = true;
+                    }
+
+                    // reload from disk
+                    if imgui::MenuItem::new(im_str!("Reload from disk")).build(&ui) {
+                        reload_selected = true;
+                    }
+
+                    // sampling method
+                    if let Some(tok) = ui.begin_menu(im_str!("Sampling Method"), true) {
+                        let mut changed = false;
+                        changed |= ui.radio_button(im_str!("Nearest"), &mut view.filter_method, FilterMethod::Nearest);
+                        changed |= ui.radio_button(im_str!("Linear"), &mut view.filter_method, FilterMethod::Linear);
+                        changed |= ui.radio_button(im_str!("Trilinear"), &mut view.filter_method, FilterMethod::Trilinear);
+                        changed |= ui.radio_button(im_str!("Lanczos"), &mut view.filter_method, FilterMethod::Lanczos);
+                        tok.end(&ui);
     
-                        // sampling method
-                        if let Some(tok) = ui.begin_menu(im_str!("Sampling Method"), true) {
-                            let mut changed = false;
-                            changed |= ui.radio_button(im_str!("Nearest"), &mut view.filter_method, FilterMethod::Nearest);
-                            changed |= ui.radio_button(im_str!("Linear"), &mut view.filter_method, FilterMethod::Linear);
-                            tok.end(&ui);
-        
-                            if changed {
-                                view.set_filter_menthod(view.filter_method);
-                            }
-                        }
-        
-                        // enable history
-                        if imgui::MenuItem::new(im_str!("History")).selected(view.history_enabled).build(&ui) {
-                            view.history_enabled = !view.history_enabled;
-                        }
-                    });
-                } else {
-                    ui.popup(context_menu_id, || {
-                        // open
-                        if imgui::MenuItem::new(im_str!("Open")).build(&ui) {
-                            open_file_open_dialog = true;
+                        if changed {
+                            view_error = view.set_filter_menthod(view.filter_method).err();
                         }
-                    });
-                }
+                    }
+    
+                    // enable history
+                    if imgui::MenuItem::new(im_str!("History")).selected(view.history_enabled).build(&ui) {
+                        view.history_enabled = !view.history_enabled;
+                    }
+                });
+            } else {
+                ui.popup(context_menu_id, || {
+                    // open
+                    if imgui::MenuItem::new(im_str!("Open")).build(&ui) {
+                        open_file_open_dialog = true;
+                    }
 
-                if right_clicked {
-                    ui.open_popup(context_menu_id);
-                }
-            }
+                    if imgui::MenuItem::new(im_str!("Command Palette")).build(&ui) {
+                        open_command_palette = true;
+                    }
 
-            let context_menu_open = unsafe {
-                imgui::sys::igIsPopupOpen(std::ffi::CString::new("ContextMenu").unwrap().as_ptr())
-            };
+                    if imgui::MenuItem::new(im_str!("Notification Log")).build(&ui) {
+                        toggle_notification_log = true;
+                    }
 
-            let view_count = self.views.len();
-            let mut next_selected = self.selected;
-            for (i, view) in self.views.iter_mut().enumerate() {
-                let border_color = match (view.selected && !self.show_titlebars, view_count) {
-                    (true, 1) =>  [0.2, 0.2, 0.2, 1.0],
-                    (true, _) =>  [1.0, 1.0, 1.0, 1.0],
-                    (false, _) => [0.2, 0.2, 0.2, 1.0],
-                };
-                let tok = ui.push_style_color(imgui::StyleColor::Border, border_color);
+                    ui.separator();
 
-                let allow_focus = !self.open_file_dialog.is_open() && !context_menu_open && self.error_msg.is_none();
-                if view.render(&ui, self.show_titlebars, allow_focus) && allow_focus {
-                    next_selected = i;
-                }
-                tok.pop(&ui);
+                    if imgui::MenuItem::new(im_str!("Save Session")).build(&ui) {
+                        save_session = true;
+                    }
+
+                    if imgui::MenuItem::new(im_str!("Load Session")).build(&ui) {
+                        load_session = true;
+                    }
+
+                    layout_menu(&ui, &mut layout_kind);
+                    group_menu(&ui, &mut group_name, &mut new_group);
+                });
             }
 
-            if self.views.len() > 0 {
-                self.views[self.selected].selected = false;
-                self.selected = next_selected;
-                self.views[self.selected].selected = true;
+            if right_clicked {
+                ui.open_popup(context_menu_id);
             }
 
-            // open file
-            let file_to_open = self.open_file_dialog.render(&ui, self.window.drawable_size(), mouse_moved);
+            let group = &mut self.groups[self.group];
+            group.layout_kind = layout_kind;
+            if group_name.to_str() != group.name {
+                group.name = group_name.to_str().to_owned();
+            }
+        }
+
+        let context_menu_open = unsafe {
+            imgui::sys::igIsPopupOpen(std::ffi::CString::new("ContextMenu").unwrap().as_ptr())
+        };
+
+        let view_count = shown.len();
+        let mut next_selected = self.selected;
+        for (i, view) in self.views[shown.clone()].iter_mut().enumerate() {
+            let border_color = match (view.selected && !self.show_titlebars, view_count) {
+                (true, 1) =>  [0.2, 0.2, 0.2, 1.0],
+                (true, _) =>  [1.0, 1.0, 1.0, 1.0],
+                (false, _) => [0.2, 0.2, 0.2, 1.0],
+            };
+            let tok = ui.push_style_color(imgui::StyleColor::Border, border_color);
+
+            let allow_focus = !self.open_file_dialog.is_open() && !self.command_palette.is_open() && !context_menu_open;
+            if view.render(&ui, self.show_titlebars, allow_focus, &self.keymap) && allow_focus {
+                next_selected = shown.start + i;
+            }
+            tok.pop(&ui);
+        }
+
+        if !shown.is_empty() {
+            self.views[self.selected].selected = false;
+            self.selected = next_selected;
+            self.views[self.selected].selected = true;
+        }
 
-            // dummy window so contex menu works
-            imgui::Window::new(imgui::im_str!("i"))
+        // tabs to switch between groups
+        if self.groups.len() > 1 {
+            let (groups, active) = (&self.groups, self.group);
+            imgui::Window::new(imgui::im_str!("Groups"))
+                .position([0.0, 0.0], imgui::Condition::Always)
+                .size([window_size.0 as f32, TAB_BAR_HEIGHT as f32], imgui::Condition::Always)
+                .title_bar(false)
+                .resizable(false)
+                .movable(false)
+                .scroll_bar(false)
                 .focus_on_appearing(false)
-                .focused(self.views.len() == 0 && !context_menu_open && !self.open_file_dialog.is_open() && self.error_msg.is_none())
-                .position([-100.0, -100.0], imgui::Condition::Always)
-                .size([0.0, 0.0], imgui::Condition::Always)
-                .build(&ui, ||{});
-
-            unsafe {
-                gl::ClearColor(0.3, 0.3, 0.5, 1.0);
-                gl::Clear(gl::COLOR_BUFFER_BIT);
-            }
-
-            // error message
-            let mut show_err = false;
-            match &self.error_msg {
-                Some(msg) => {
-                    show_err = true;
-                    ui.popup_modal(imgui::im_str!("Error"))
-                        .opened(&mut show_err)
-                        .movable(true)
-                        .resizable(true)
-                        .save_settings(true)
-                        .always_auto_resize(true)
-                        .build(|| {
-                            ui.text(msg);
-                        });
-                    if show_err {
-                        ui.open_popup(imgui::im_str!("Error"));
+                .build(&ui, || {
+                    for (index, group) in groups.iter().enumerate() {
+                        if index > 0 {
+                            ui.same_line(0.0);
+                        }
+                        let color = if index == active { [0.4, 0.4, 0.7, 1.0] } else { [0.2, 0.2, 0.3, 1.0] };
+                        let tok = ui.push_style_color(imgui::StyleColor::Button, color);
+                        let label = imgui::ImString::new(format!("{}##group{}", group.name, index));
+                        if ui.button(&label, [0.0, 0.0]) {
+                            clicked_group = Some(index);
+                        }
+                        tok.pop(&ui);
                     }
-                },
-                None => {},
-            }
+                });
+        }
+
+        // dividers of the split layout
+        let allow_drag = !self.open_file_dialog.is_open() && !self.command_palette.is_open() && !context_menu_open;
+        let group = &mut self.groups[self.group];
+        if group.layout_kind == LayoutKind::Split && allow_drag {
+            let [mouse_x, mouse_y] = ui.io().mouse_pos;
+            let (mouse_x, mouse_y) = (mouse_x as i32, mouse_y as i32 - tab_bar_height);
 
-            if !show_err {
-                self.error_msg = None;
+            if ui.is_mouse_clicked(imgui::MouseButton::Left) {
+                self.dragged_divider = group.split_layout.divider_at(mouse_x, mouse_y, area_width, area_height);
             }
-            
-            // render window contents here
-            self.imgui_sdl2.prepare_render(&ui, &self.window);
-            self.opengl_renderer.render(ui);
-            self.window.gl_swap_window();
-
-            match file_to_open {
-                Some(file_to_open) => {
-                    let _ = self.open_image(&file_to_open, false);
+            if !ui.is_mouse_down(imgui::MouseButton::Left) {
+                self.dragged_divider = None;
+            }
+
+            let divider = match &self.dragged_divider {
+                Some(divider) => {
+                    let position = match divider.direction {
+                        LayoutDirection::Horizontal => mouse_x,
+                        LayoutDirection::Vertical => mouse_y,
+                    };
+                    group.split_layout.drag(divider, position);
+                    Some(divider.direction)
                 },
+                None => group.split_layout.divider_at(mouse_x, mouse_y, area_width, area_height).map(|divider| divider.direction),
+            };
+            match divider {
+                Some(LayoutDirection::Horizontal) => ui.set_mouse_cursor(Some(imgui::MouseCursor::ResizeEW)),
+                Some(LayoutDirection::Vertical) => ui.set_mouse_cursor(Some(imgui::MouseCursor::ResizeNS)),
                 None => {},
             }
+        } else {
+            self.dragged_divider = None;
+        }
 
-            if open_file_open_dialog {
-                self.open_file_open_dialog();
-            }
+        // open file
+        let file_to_open = self.open_file_dialog.render(&ui, drawable_size, mouse_moved);
 
-            if close_view && self.selected < self.views.len() {
-                self.views.remove(self.selected);
-                if !self.views.is_empty() {
-                    self.selected = self.selected % self.views.len();
-                    self.views[self.selected].selected = true;
-                } else {
-                    self.selected = 0;
-                }
+        // command palette
+        let palette_action = self.command_palette.render(&ui, drawable_size, &self.keymap);
+
+        // dummy window so contex menu works
+        imgui::Window::new(imgui::im_str!("i"))
+            .focus_on_appearing(false)
+            .focused(shown.is_empty() && !context_menu_open && !self.open_file_dialog.is_open() && !self.command_palette.is_open())
+            .position([-100.0, -100.0], imgui::Condition::Always)
+            .size([0.0, 0.0], imgui::Condition::Always)
+            .build(&ui, ||{});
+
+        // errors
+        self.notifications.render(&ui, window_size);
+
+        if let Some(log_viewer) = &mut self.log_viewer {
+            log_viewer.render(&ui, window_size);
+        }
+
+        self.debug_overlay.render(&ui, self.views.len(), self.texture_budget.bytes());
+
+        // render window contents here
+        if let Platform::Window(window) = &mut self.platform {
+            window.imgui_sdl2.prepare_render(&ui, &window.window);
+        }
+        renderer::with(|renderer| renderer.render(ui, CLEAR_COLOR));
+        if let Platform::Window(window) = &self.platform {
+            window.window.gl_swap_window();
+        }
+
+        match file_to_open {
+            Some(file_to_open) => if let Err(err) = self.open_image(&file_to_open, false) {
+                self.notifications.error(err.to_string());
+            },
+            None => {},
+        }
+
+        match palette_action {
+            Some(Action::Quit) => return false,
+            Some(action) => self.handle_action(action, &mut close_view, &mut open_file_open_dialog),
+            None => {},
+        }
+
+        if open_file_open_dialog {
+            self.open_file_open_dialog();
+        }
+
+        if open_command_palette {
+            self.command_palette.open();
+        }
+
+        if toggle_notification_log {
+            self.notifications.toggle_history();
+        }
+
+        if save_session {
+            self.save_session_to_default();
+        }
+
+        if load_session {
+            self.load_session_from_default();
+        }
+
+        if let Some(err) = view_error {
+            self.notifications.error(err.to_string());
+        }
+
+        if reload_selected && self.has_selected() {
+            if let Err(err) = self.reload_in_place(self.selected, None) {
+                self.notifications.error(err.to_string());
             }
         }
+
+        if let Some(group) = clicked_group {
+            self.show_group(group);
+        }
+
+        if new_group {
+            self.new_group();
+        }
+
+        if close_view && self.has_selected() {
+            self.close_view(self.selected);
+        }
+
+        true
     }
 }
+
+/// Textures are deleted before the renderer, and the renderer before the GL context it draws with.
+impl Drop for App {
+    fn drop(&mut self) {
+        self.views.clear();
+        self.loaded_images.clear();
+        renderer::set_current(None);
+    }
+}
+
+fn layout_menu(ui: &imgui::Ui, layout_kind: &mut LayoutKind) {
+    use imgui::*;
+
+    if let Some(tok) = ui.begin_menu(im_str!("Layout"), true) {
+        ui.radio_button(im_str!("Grid"), layout_kind, LayoutKind::Grid);
+        ui.radio_button(im_str!("Rows"), layout_kind, LayoutKind::Rows);
+        ui.radio_button(im_str!("Masonry"), layout_kind, LayoutKind::Masonry);
+        ui.radio_button(im_str!("Focus"), layout_kind, LayoutKind::Focus);
+        ui.radio_button(im_str!("Split"), layout_kind, LayoutKind::Split);
+        tok.end(ui);
+    }
+}
+
+fn group_menu(ui: &imgui::Ui, name: &mut imgui::ImString, new_group: &mut bool) {
+    use imgui::*;
+
+    if let Some(tok) = ui.begin_menu(im_str!("Group"), true) {
+        ui.input_text(im_str!("Name"), name).build();
+        if MenuItem::new(im_str!("New Group")).build(ui) {
+            *new_group = true;
+        }
+        tok.end(ui);
+    }
+}
+
+fn create_imgui() -> imgui::Context {
+    let mut imgui = imgui::Context::create();
+    imgui.set_ini_filename(None);
+    imgui.style_mut().window_rounding = 0.0;
+    imgui.style_mut().window_border_size = 1.0;
+    imgui
+}
+
+fn not_open_message(path: &Option<PathBuf>) -> String {
+    match path {
+        Some(path) => format!("Image {:?} is not open", path),
+        None => "No image is open".to_owned(),
+    }
+}
+
+/// Modifiers held alone do nothing, e.g. while the next key of a shortcut is pressed, so they don't keep the loop drawing.
+fn is_modifier(scancode: sdl2::keyboard::Scancode) -> bool {
+    use sdl2::keyboard::Scancode::*;
+    matches!(scancode, LCtrl | RCtrl | LShift | RShift | LAlt | RAlt | LGui | RGui)
+}
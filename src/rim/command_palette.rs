@@ -0,0 +1,208 @@
+use imgui::*;
+
+use super::action::Action;
+use super::keymap::KeyMap;
+
+pub struct CommandPalette {
+    query       : ImString,
+    matches     : Vec<Action>,
+    selected    : usize,
+    open        : bool,
+}
+
+impl CommandPalette {
+    pub fn new() -> CommandPalette {
+        CommandPalette {
+            query       : ImString::with_capacity(256),
+            matches     : Vec::new(),
+            selected    : 0,
+            open        : false,
+        }
+    }
+
+    /// Returns the action chosen by the user, the palette closes itself in that case.
+    pub fn render(&mut self, ui: &Ui, max_size: (u32, u32), keymap: &KeyMap) -> Option<Action> {
+        let mut result = None;
+        let mut open = self.open;
+        use imgui::sys::ImVec2;
+        unsafe {
+            imgui::sys::igSetNextWindowSize(
+                ImVec2::new(std::cmp::min(600, max_size.0) as f32, std::cmp::min(400, max_size.1) as f32),
+                imgui::Condition::Always as imgui::sys::ImGuiCond);
+        }
+
+        let id = im_str!("Command Palette");
+
+        ui.popup_modal(id)
+            .opened(&mut open)
+            .movable(true)
+            .resizable(true)
+            .save_settings(true)
+            .build(|| {
+                // keep the keyboard focus in the search field, the list is navigated with the arrow keys
+                unsafe {
+                    imgui::sys::igSetKeyboardFocusHere(0);
+                }
+                if ui.input_text(im_str!("##query"), &mut self.query).build() {
+                    self.update_matches();
+                }
+                ui.separator();
+
+                let mut selected = self.selected;
+                if ui.is_window_focused() && !self.matches.is_empty() {
+                    if ui.is_key_pressed(sdl2::keyboard::Scancode::Up as u32) {
+                        selected = (selected + self.matches.len() - 1) % self.matches.len();
+                    }
+                    if ui.is_key_pressed(sdl2::keyboard::Scancode::Down as u32) {
+                        selected = (selected + 1) % self.matches.len();
+                    }
+                    if ui.is_key_pressed(sdl2::keyboard::Scancode::Return as u32) {
+                        result = self.matches.get(selected).copied();
+                    }
+                }
+                if ui.is_key_pressed(sdl2::keyboard::Scancode::Escape as u32) {
+                    self.close();
+                }
+
+                let binding_column = ui.window_content_region_max()[0] * 0.65;
+                for (i, action) in self.matches.iter().enumerate() {
+                    let label = im_str!("{}##{}", action.description(), action.name());
+                    if Selectable::new(&label)
+                        .flags(SelectableFlags::DONT_CLOSE_POPUPS)
+                        .selected(i == selected)
+                        .build(ui) {
+                        result = Some(*action);
+                    }
+
+                    if i == selected && !ui.is_item_visible() {
+                        unsafe {
+                            imgui::sys::igSetScrollHereY(0.5);
+                        }
+                    }
+
+                    ui.same_line(binding_column);
+                    ui.text_disabled(&keymap.bindings_text(*action));
+                }
+
+                self.selected = selected;
+            });
+
+        self.open &= open;
+
+        if result.is_some() {
+            self.close();
+        }
+
+        if self.open {
+            ui.open_popup(id);
+        }
+
+        result
+    }
+
+    fn update_matches(&mut self) {
+        let query = self.query.to_str();
+
+        let mut scored: Vec<(i32, Action)> = Action::ALL.iter()
+            .filter(|action| !action.is_continuous())
+            .filter_map(|action| {
+                let text = format!("{} {}", action.description(), action.name());
+                fuzzy_score(query, &text).map(|score| (score, *action))
+            })
+            .collect();
+
+        // sort_by_key is stable, so equally good matches stay in registry order
+        scored.sort_by_key(|(score, _)| -score);
+
+        self.matches = scored.into_iter().map(|(_, action)| action).collect();
+        self.selected = 0;
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    pub fn open(&mut self) {
+        self.open = true;
+        self.query.clear();
+        self.update_matches();
+    }
+
+    pub fn close(&mut self) {
+        self.open = false;
+    }
+}
+
+/// Scores how well `text` matches `pattern` when the characters of `pattern` appear in `text` in
+/// order, with bonuses for consecutive characters and characters at the start of a word.
+/// Case is ignored. Returns `None` if `text` doesn't contain all characters of `pattern`.
+fn fuzzy_score(pattern: &str, text: &str) -> Option<i32> {
+    let mut score = 0;
+    let mut pattern_chars = pattern.chars().filter(|c| !c.is_whitespace()).flat_map(char::to_lowercase).peekable();
+    let mut prev_matched = false;
+    let mut prev_char = ' ';
+
+    for c in text.chars().flat_map(char::to_lowercase) {
+        match pattern_chars.peek() {
+            Some(&p) if p == c => {
+                score += 1;
+                if prev_matched {
+                    score += 5;
+                }
+                if !prev_char.is_alphanumeric() {
+                    score += 10;
+                }
+                prev_matched = true;
+                pattern_chars.next();
+            },
+            Some(_) => prev_matched = false,
+            None => break,
+        }
+        prev_char = c;
+    }
+
+    if pattern_chars.peek().is_none() {
+        Some(score)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn word_start_scores_higher() {
+        assert!(fuzzy_score("l", "a log").unwrap() > fuzzy_score("l", "also").unwrap());
+    }
+
+    #[test]
+    fn consecutive_matches_score_higher() {
+        assert!(fuzzy_score("ab", "xabx").unwrap() > fuzzy_score("ab", "xaxb").unwrap());
+    }
+
+    #[test]
+    fn case_is_ignored() {
+        assert_eq!(fuzzy_score("RELOAD", "reload image"), fuzzy_score("reload", "Reload Image"));
+        assert!(fuzzy_score("Rl", "reload").is_some());
+    }
+
+    #[test]
+    fn whitespace_in_pattern_is_ignored() {
+        assert_eq!(fuzzy_score("re load", "reload"), fuzzy_score("reload", "reload"));
+    }
+
+    #[test]
+    fn non_matching_is_none() {
+        assert_eq!(fuzzy_score("xyz", "reload"), None);
+        // characters must appear in order
+        assert_eq!(fuzzy_score("ba", "ab"), None);
+        assert_eq!(fuzzy_score("reloads", "reload"), None);
+    }
+
+    #[test]
+    fn empty_pattern_matches_everything() {
+        assert_eq!(fuzzy_score("", "reload"), Some(0));
+    }
+}
@@ -0,0 +1,137 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::*;
+
+use serde::Deserialize;
+
+use super::action::Action;
+use super::keymap::{KeyBinding, KeyMap};
+use super::layout::EdgeBehavior;
+
+/// Layout of the config file, e.g.
+///
+/// ```toml
+/// edges = "stop"
+///
+/// [bindings]
+/// quit = ["Ctrl+Q"]
+/// pan_up = ["W", "Up"]
+/// reload = []
+/// ```
+///
+/// Every action listed in `bindings` replaces all default bindings of that action.
+#[derive(Deserialize)]
+struct ConfigFile {
+    #[serde(default)]
+    edges   : Option<EdgeBehavior>,
+    #[serde(default)]
+    bindings: HashMap<String, Vec<String>>,
+}
+
+#[derive(Default)]
+pub struct Config {
+    pub keymap  : KeyMap,
+    /// Whether moving the selection past the last view of a row or column wraps around or stops.
+    pub edges   : EdgeBehavior,
+}
+
+impl Config {
+    /// Default location of the config file, `<config dir>/rim/config.toml`.
+    pub fn default_path() -> Option<PathBuf> {
+        config_dir().map(|dir| dir.join("rim").join("config.toml"))
+    }
+
+    /// Loads the config file at `path` on top of the defaults.
+    /// A missing file is not an error, invalid entries are reported and skipped.
+    pub fn load(path: &Path) -> Result<Config, String> {
+        let mut config = Config::default();
+
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(ref err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(config),
+            Err(err) => return Err(format!("Failed to read config file {:?}: {}", path, err)),
+        };
+
+        let file: ConfigFile = match toml::from_str(&text) {
+            Ok(file) => file,
+            Err(err) => return Err(format!("Failed to parse config file {:?}: {}", path, err)),
+        };
+
+        if let Some(edges) = file.edges {
+            config.edges = edges;
+        }
+
+        for (name, bindings) in file.bindings.iter() {
+            let action = match Action::from_name(name) {
+                Some(action) => action,
+                None => {
+                    log::warn!("Config {:?}: unknown action '{}'", path, name);
+                    continue;
+                }
+            };
+
+            config.keymap.unbind_action(action);
+            for binding in bindings.iter() {
+                match KeyBinding::parse(binding) {
+                    Ok(binding) => config.keymap.bind(binding, action),
+                    Err(msg) => log::warn!("Config {:?}: {}", path, msg),
+                }
+            }
+        }
+
+        Ok(config)
+    }
+}
+
+/// The platform's directory for per-user configuration files.
+pub fn config_dir() -> Option<PathBuf> {
+    use std::env::var_os;
+
+    if cfg!(windows) {
+        var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        var_os("HOME").map(|home| PathBuf::from(home).join("Library").join("Application Support"))
+    } else {
+        match var_os("XDG_CONFIG_HOME") {
+            Some(dir) if !dir.is_empty() => Some(PathBuf::from(dir)),
+            _ => var_os("HOME").map(|home| PathBuf::from(home).join(".config")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sdl2::keyboard::Scancode;
+
+    fn load(name: &str, text: &str) -> Config {
+        let path = std::env::temp_dir().join(format!("rim-config-test-{}-{}.toml", name, std::process::id()));
+        fs::write(&path, text).unwrap();
+        let config = Config::load(&path);
+        fs::remove_file(&path).unwrap();
+        config.unwrap()
+    }
+
+    #[test]
+    fn missing_file_gives_defaults() {
+        let config = Config::load(Path::new("/nonexistent/rim/config.toml")).unwrap();
+        assert_eq!(config.edges, EdgeBehavior::Wrap);
+        assert_eq!(config.keymap.bindings_text(Action::Reload), KeyMap::default().bindings_text(Action::Reload));
+    }
+
+    #[test]
+    fn bindings_replace_defaults_of_action() {
+        let config = load("replace", "edges = \"stop\"\n[bindings]\nreload = [\"F6\"]\nquit = []\n");
+        assert_eq!(config.edges, EdgeBehavior::Stop);
+        assert_eq!(config.keymap.bindings_for(Action::Reload), [KeyBinding::new(Scancode::F6)]);
+        assert!(config.keymap.bindings_for(Action::Quit).is_empty());
+        // actions which aren't listed keep their defaults
+        assert_eq!(config.keymap.bindings_text(Action::OpenFile), KeyMap::default().bindings_text(Action::OpenFile));
+    }
+
+    #[test]
+    fn invalid_bindings_are_skipped() {
+        let config = load("invalid", "[bindings]\nreload = [\"Ctrl+\", \"F6\"]\nno_such_action = [\"F7\"]\n");
+        assert_eq!(config.keymap.bindings_for(Action::Reload), [KeyBinding::new(Scancode::F6)]);
+    }
+}
@@ -0,0 +1,45 @@
+use imgui::*;
+
+use super::image::texture_stats;
+
+const MIB: f64 = 1024.0 * 1024.0;
+
+/// Small window in the top left corner with resource usage, to spot leaks in long sessions.
+pub struct DebugOverlay {
+    open    : bool,
+}
+
+impl DebugOverlay {
+    pub fn new() -> DebugOverlay {
+        DebugOverlay {
+            open    : false,
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+    }
+
+    pub fn render(&mut self, ui: &Ui, view_count: usize, budget: usize) {
+        if !self.open {
+            return;
+        }
+
+        let stats = texture_stats();
+        let framerate = ui.io().framerate;
+
+        Window::new(im_str!("##DebugOverlay"))
+            .position([10.0, 10.0], Condition::Always)
+            .always_auto_resize(true)
+            .focus_on_appearing(false)
+            .title_bar(false)
+            .resizable(false)
+            .bg_alpha(0.6)
+            .build(ui, || {
+                ui.text(&im_str!("Views:    {}", view_count));
+                ui.text(&im_str!("Textures: {}", stats.count));
+                ui.text(&im_str!("Memory:   {:.1} / {:.1} MiB", stats.bytes as f64 / MIB, budget as f64 / MIB));
+                ui.text(&im_str!("Frame:    {:.1} ms", 1000.0 / framerate.max(1.0)));
+            });
+    }
+}
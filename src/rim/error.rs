@@ -0,0 +1,64 @@
+use std::fmt;
+use std::io;
+use std::path::*;
+
+/// Errors of loading, decoding and displaying images. The messages name the file and
+/// the reason, so they can be shown to the user as they are.
+#[derive(Debug)]
+pub enum RimError {
+    Io { path: PathBuf, source: io::Error },
+    Decode { path: PathBuf, source: image::ImageError },
+    UnsupportedFormat { path: PathBuf },
+    /// The image has no file it could be reloaded from, e.g. it was read from stdin.
+    NotReloadable { path: PathBuf },
+    Gl(String),
+    /// Watching a file for changes failed, or without a path, creating the watcher failed.
+    Watcher { path: Option<PathBuf>, source: notify::Error },
+}
+
+impl RimError {
+    pub fn io(path: &Path, source: io::Error) -> RimError {
+        RimError::Io { path: path.to_owned(), source }
+    }
+
+    pub fn image(path: &Path, source: image::ImageError) -> RimError {
+        match source {
+            image::ImageError::IoError(source) => RimError::io(path, source),
+            image::ImageError::Unsupported(_) => RimError::UnsupportedFormat { path: path.to_owned() },
+            source => RimError::Decode { path: path.to_owned(), source },
+        }
+    }
+
+    pub fn watcher(path: Option<&Path>, source: notify::Error) -> RimError {
+        RimError::Watcher { path: path.map(Path::to_owned), source }
+    }
+}
+
+impl fmt::Display for RimError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RimError::Io { path, source } => match source.kind() {
+                io::ErrorKind::NotFound => write!(f, "{:?} doesn't exist", path),
+                io::ErrorKind::PermissionDenied => write!(f, "No permission to read {:?}", path),
+                _ => write!(f, "Failed to read {:?}: {}", path, source),
+            },
+            RimError::Decode { path, source } => write!(f, "Failed to decode {:?}, the file may be damaged: {}", path, source),
+            RimError::UnsupportedFormat { path } => write!(f, "{:?} is not in a supported image format", path),
+            RimError::NotReloadable { path } => write!(f, "{:?} was not read from a file and can't be reloaded", path),
+            RimError::Gl(msg) => write!(f, "OpenGL error: {}", msg),
+            RimError::Watcher { path: Some(path), source } => write!(f, "Changes to {:?} won't be reloaded automatically: {}", path, source),
+            RimError::Watcher { path: None, source } => write!(f, "Changed files won't be reloaded automatically: {}", source),
+        }
+    }
+}
+
+impl std::error::Error for RimError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RimError::Io { source, .. } => Some(source),
+            RimError::Decode { source, .. } => Some(source),
+            RimError::Watcher { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,135 @@
+use super::error::RimError;
+use super::renderer::{Renderer, TextureFilter};
+
+/// Draws with OpenGL into the window the GL context was created for. Textures are GL texture names.
+pub struct GlRenderer {
+    imgui_renderer  : imgui_opengl_renderer::Renderer,
+}
+
+impl GlRenderer {
+    /// Loads the GL functions, the GL context must be current.
+    pub fn new(imgui: &mut imgui::Context, video_subsystem: &sdl2::VideoSubsystem) -> GlRenderer {
+        gl::load_with(|s| video_subsystem.gl_get_proc_address(s) as _);
+        if cfg!(debug_assertions) {
+            super::opengl_macros::enable_debug_output();
+        }
+
+        let imgui_renderer = imgui_opengl_renderer::Renderer::new(imgui, |s| {
+            video_subsystem.gl_get_proc_address(s) as _
+        });
+
+        GlRenderer {
+            imgui_renderer,
+        }
+    }
+}
+
+impl Renderer for GlRenderer {
+    fn create_texture(&mut self) -> Result<usize, RimError> {
+        let mut tex_id: u32 = 0;
+        GL!(GenTextures(1, &mut tex_id));
+        if tex_id == 0 {
+            return Err(RimError::Gl("Failed to create a texture".to_owned()));
+        }
+        GL!(BindTexture(TEXTURE_2D, tex_id));
+        GL!(TexParameteri(TEXTURE_2D, TEXTURE_MIN_FILTER, NEAREST as i32));
+        GL!(TexParameteri(TEXTURE_2D, TEXTURE_MAG_FILTER, NEAREST as i32));
+        GL!(TexParameteri(TEXTURE_2D, TEXTURE_WRAP_S, CLAMP_TO_EDGE as i32));
+        GL!(TexParameteri(TEXTURE_2D, TEXTURE_WRAP_T, CLAMP_TO_EDGE as i32));
+        GL!(BindTexture(TEXTURE_2D, 0));
+
+        Ok(tex_id as usize)
+    }
+
+    fn upload_texture(&mut self, texture: usize, level: u32, pixels: &image::RgbaImage) {
+        GL!(BindTexture(TEXTURE_2D, texture as u32));
+        GL!(PixelStorei(UNPACK_ALIGNMENT, 1));
+        GL!(TexImage2D(
+            TEXTURE_2D,
+            level as i32,
+            RGBA8 as i32,
+            pixels.width() as i32,
+            pixels.height() as i32,
+            0,
+            RGBA,
+            UNSIGNED_BYTE,
+            pixels.as_ptr() as *const std::ffi::c_void
+        ));
+        GL!(BindTexture(TEXTURE_2D, 0));
+    }
+
+    fn generate_mipmaps(&mut self, texture: usize) {
+        GL!(BindTexture(TEXTURE_2D, texture as u32));
+        GL!(GenerateMipmap(TEXTURE_2D));
+        GL!(BindTexture(TEXTURE_2D, 0));
+    }
+
+    fn read_texture(&mut self, texture: usize) -> image::RgbaImage {
+        let (mut width, mut height) = (0, 0);
+        GL!(BindTexture(TEXTURE_2D, texture as u32));
+        GL!(GetTexLevelParameteriv(TEXTURE_2D, 0, TEXTURE_WIDTH, &mut width));
+        GL!(GetTexLevelParameteriv(TEXTURE_2D, 0, TEXTURE_HEIGHT, &mut height));
+
+        let mut data = vec![0u8; width as usize * height as usize * 4];
+        GL!(PixelStorei(PACK_ALIGNMENT, 1));
+        GL!(GetTexImage(TEXTURE_2D, 0, RGBA, UNSIGNED_BYTE, data.as_mut_ptr() as *mut std::ffi::c_void));
+        GL!(BindTexture(TEXTURE_2D, 0));
+
+        match image::RgbaImage::from_raw(width as u32, height as u32, data) {
+            Some(pixels) => pixels,
+            None => image::RgbaImage::new(1, 1),
+        }
+    }
+
+    fn set_filter(&mut self, texture: usize, min_filter: TextureFilter, mag_filter: TextureFilter) {
+        GL!(BindTexture(TEXTURE_2D, texture as u32));
+        GL!(TexParameteri(TEXTURE_2D, TEXTURE_MIN_FILTER, gl_filter(min_filter) as i32));
+        GL!(TexParameteri(TEXTURE_2D, TEXTURE_MAG_FILTER, gl_filter(mag_filter) as i32));
+        GL!(BindTexture(TEXTURE_2D, 0));
+    }
+
+    fn delete_texture(&mut self, texture: usize) {
+        let tex_id = texture as u32;
+        GL!(DeleteTextures(1, &tex_id));
+    }
+
+    fn max_texture_size(&mut self) -> u32 {
+        let mut max_size: i32 = 0;
+        GL!(GetIntegerv(MAX_TEXTURE_SIZE, &mut max_size));
+        if max_size > 0 {
+            max_size as u32
+        } else {
+            u32::MAX
+        }
+    }
+
+    fn render(&mut self, ui: imgui::Ui, clear_color: [f32; 4]) {
+        GL!(ClearColor(clear_color[0], clear_color[1], clear_color[2], clear_color[3]));
+        GL!(Clear(COLOR_BUFFER_BIT));
+        self.imgui_renderer.render(ui);
+    }
+
+    fn read_frame(&mut self) -> image::RgbaImage {
+        let mut viewport = [0i32; 4];
+        GL!(GetIntegerv(VIEWPORT, viewport.as_mut_ptr()));
+        let (width, height) = (viewport[2].max(1) as u32, viewport[3].max(1) as u32);
+
+        let mut data = vec![0u8; width as usize * height as usize * 4];
+        GL!(PixelStorei(PACK_ALIGNMENT, 1));
+        GL!(ReadPixels(viewport[0], viewport[1], width as i32, height as i32, RGBA, UNSIGNED_BYTE, data.as_mut_ptr() as *mut std::ffi::c_void));
+
+        // OpenGL starts with the bottom row
+        match image::RgbaImage::from_raw(width, height, data) {
+            Some(frame) => image::imageops::flip_vertical(&frame),
+            None => image::RgbaImage::new(1, 1),
+        }
+    }
+}
+
+fn gl_filter(filter: TextureFilter) -> gl::types::GLenum {
+    match filter {
+        TextureFilter::Nearest => gl::NEAREST,
+        TextureFilter::Linear => gl::LINEAR,
+        TextureFilter::LinearMipmapLinear => gl::LINEAR_MIPMAP_LINEAR,
+    }
+}
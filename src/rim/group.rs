@@ -0,0 +1,107 @@
+use std::ops::Range;
+
+use super::layout::{FocusLayout, GridLayout, Layout, LayoutDirection, LayoutKind, MasonryLayout, RowLayout};
+use super::split_layout::SplitLayout;
+
+/// Views shown together with their own layout, like a tab. Only the views of one group are shown at a time.
+///
+/// The views of all groups are kept in one list, one group after the other, so they can still be found by path
+/// and reloaded while their group isn't shown.
+pub struct ViewGroup {
+    pub name            : String,
+    pub view_count      : usize,
+    /// Index of the selected view within the group, kept while another group is shown.
+    pub selected        : usize,
+
+    pub layout_kind     : LayoutKind,
+    /// Kept while other layouts are used, so the panes aren't lost when switching back.
+    pub split_layout    : SplitLayout,
+    pub layout_direction: LayoutDirection,
+    pub auto_layout_dir : bool,
+}
+
+impl ViewGroup {
+    pub fn new(name: String) -> ViewGroup {
+        ViewGroup {
+            name,
+            view_count      : 0,
+            selected        : 0,
+
+            layout_kind     : LayoutKind::Grid,
+            split_layout    : SplitLayout::default(),
+            layout_direction: LayoutDirection::Vertical,
+            auto_layout_dir : true,
+        }
+    }
+
+    /// The layout the views are arranged in, only the split layout has state.
+    pub fn current_layout(&self) -> &dyn Layout {
+        match self.layout_kind {
+            LayoutKind::Grid => &GridLayout {},
+            LayoutKind::Rows => &RowLayout {},
+            LayoutKind::Masonry => &MasonryLayout {},
+            LayoutKind::Focus => &FocusLayout {},
+            LayoutKind::Split => &self.split_layout,
+        }
+    }
+}
+
+/// Name of a new group, numbered after the groups there are.
+pub fn default_name(groups: &[ViewGroup]) -> String {
+    (1 ..)
+        .map(|number| format!("Group {}", number))
+        .find(|name| groups.iter().all(|group| &group.name != name))
+        .unwrap_or_default()
+}
+
+/// Indices of the views of `group` in the list of all views.
+pub fn view_range(groups: &[ViewGroup], group: usize) -> Range<usize> {
+    let start: usize = groups[.. group].iter().map(|group| group.view_count).sum();
+    start .. start + groups[group].view_count
+}
+
+/// Group of the view at `index` in the list of all views.
+pub fn group_of(groups: &[ViewGroup], index: usize) -> Option<usize> {
+    let mut end = 0;
+    groups.iter().position(|group| {
+        end += group.view_count;
+        index < end
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn groups(view_counts: &[usize]) -> Vec<ViewGroup> {
+        view_counts.iter()
+            .enumerate()
+            .map(|(index, &view_count)| ViewGroup { view_count, ..ViewGroup::new(format!("Group {}", index + 1)) })
+            .collect()
+    }
+
+    #[test]
+    fn view_ranges_follow_each_other() {
+        let groups = groups(&[2, 0, 3]);
+        assert_eq!(view_range(&groups, 0), 0 .. 2);
+        assert_eq!(view_range(&groups, 1), 2 .. 2);
+        assert_eq!(view_range(&groups, 2), 2 .. 5);
+    }
+
+    #[test]
+    fn views_belong_to_their_range() {
+        let groups = groups(&[2, 0, 3]);
+        let found: Vec<_> = (0 .. 6).map(|index| group_of(&groups, index)).collect();
+        assert_eq!(found, [Some(0), Some(0), Some(2), Some(2), Some(2), None]);
+    }
+
+    #[test]
+    fn default_name_is_unused() {
+        assert_eq!(default_name(&[]), "Group 1");
+        let mut groups = groups(&[1, 1, 1]);
+        groups.remove(1);
+        assert_eq!(default_name(&groups), "Group 2");
+        groups.push(ViewGroup::new(default_name(&groups)));
+        assert_eq!(default_name(&groups), "Group 4");
+    }
+}
@@ -0,0 +1,130 @@
+use imgui::Key;
+use sdl2::event::{Event, WindowEvent};
+use sdl2::keyboard::{Mod, Scancode};
+use sdl2::mouse::MouseButton;
+
+/// Time between two frames, fixed so frames only depend on the events.
+const FRAME_TIME: f32 = 1.0 / 60.0;
+
+/// Input of an app without a window, from SDL events passed in by the caller instead of read from SDL.
+/// Does for imgui what `imgui_sdl2` does for a window, and keeps track of the size the window would have.
+pub struct Headless {
+    width       : u32,
+    height      : u32,
+    /// Buttons pressed since the last frame, so clicks shorter than a frame aren't lost.
+    mouse_press : [bool; 5],
+    mouse_held  : [bool; 5],
+}
+
+impl Headless {
+    pub fn new(imgui: &mut imgui::Context, width: u32, height: u32) -> Headless {
+        let io = imgui.io_mut();
+        io[Key::Tab] = Scancode::Tab as u32;
+        io[Key::LeftArrow] = Scancode::Left as u32;
+        io[Key::RightArrow] = Scancode::Right as u32;
+        io[Key::UpArrow] = Scancode::Up as u32;
+        io[Key::DownArrow] = Scancode::Down as u32;
+        io[Key::PageUp] = Scancode::PageUp as u32;
+        io[Key::PageDown] = Scancode::PageDown as u32;
+        io[Key::Home] = Scancode::Home as u32;
+        io[Key::End] = Scancode::End as u32;
+        io[Key::Insert] = Scancode::Insert as u32;
+        io[Key::Delete] = Scancode::Delete as u32;
+        io[Key::Backspace] = Scancode::Backspace as u32;
+        io[Key::Space] = Scancode::Space as u32;
+        io[Key::Enter] = Scancode::Return as u32;
+        io[Key::Escape] = Scancode::Escape as u32;
+        io[Key::A] = Scancode::A as u32;
+        io[Key::C] = Scancode::C as u32;
+        io[Key::V] = Scancode::V as u32;
+        io[Key::X] = Scancode::X as u32;
+        io[Key::Y] = Scancode::Y as u32;
+        io[Key::Z] = Scancode::Z as u32;
+
+        Headless {
+            width,
+            height,
+            mouse_press : [false; 5],
+            mouse_held  : [false; 5],
+        }
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    pub fn set_size(&mut self, width: u32, height: u32) {
+        self.width = width;
+        self.height = height;
+    }
+
+    /// Passes keys, text and mouse input on to imgui. Resize events change the size of the frames.
+    pub fn handle_event(&mut self, imgui: &mut imgui::Context, event: &Event) {
+        let io = imgui.io_mut();
+        match event {
+            Event::KeyDown { scancode: Some(scancode), keymod, .. } => {
+                set_modifiers(io, *keymod);
+                io.keys_down[*scancode as usize] = true;
+            },
+            Event::KeyUp { scancode: Some(scancode), keymod, .. } => {
+                set_modifiers(io, *keymod);
+                io.keys_down[*scancode as usize] = false;
+            },
+            Event::TextInput { text, .. } => for c in text.chars() {
+                io.add_input_character(c);
+            },
+            Event::MouseMotion { x, y, .. } => io.mouse_pos = [*x as f32, *y as f32],
+            Event::MouseButtonDown { mouse_btn, x, y, .. } => {
+                io.mouse_pos = [*x as f32, *y as f32];
+                if let Some(button) = button_index(*mouse_btn) {
+                    self.mouse_press[button] = true;
+                    self.mouse_held[button] = true;
+                }
+            },
+            Event::MouseButtonUp { mouse_btn, x, y, .. } => {
+                io.mouse_pos = [*x as f32, *y as f32];
+                if let Some(button) = button_index(*mouse_btn) {
+                    self.mouse_held[button] = false;
+                }
+            },
+            Event::MouseWheel { x, y, .. } => {
+                io.mouse_wheel_h += *x as f32;
+                io.mouse_wheel += *y as f32;
+            },
+            Event::Window { win_event: WindowEvent::Resized(width, height), .. }
+            | Event::Window { win_event: WindowEvent::SizeChanged(width, height), .. } => {
+                self.set_size(std::cmp::max(1, *width) as u32, std::cmp::max(1, *height) as u32);
+            },
+            _ => {},
+        }
+    }
+
+    pub fn prepare_frame(&mut self, io: &mut imgui::Io) {
+        io.display_size = [self.width as f32, self.height as f32];
+        io.display_framebuffer_scale = [1.0, 1.0];
+        io.delta_time = FRAME_TIME;
+
+        for (i, down) in io.mouse_down.iter_mut().enumerate() {
+            *down = self.mouse_press[i] || self.mouse_held[i];
+        }
+        self.mouse_press = [false; 5];
+    }
+}
+
+fn set_modifiers(io: &mut imgui::Io, keymod: Mod) {
+    io.key_ctrl = keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD);
+    io.key_shift = keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD);
+    io.key_alt = keymod.intersects(Mod::LALTMOD | Mod::RALTMOD);
+    io.key_super = keymod.intersects(Mod::LGUIMOD | Mod::RGUIMOD);
+}
+
+fn button_index(button: MouseButton) -> Option<usize> {
+    match button {
+        MouseButton::Left => Some(0),
+        MouseButton::Right => Some(1),
+        MouseButton::Middle => Some(2),
+        MouseButton::X1 => Some(3),
+        MouseButton::X2 => Some(4),
+        MouseButton::Unknown => None,
+    }
+}
@@ -2,115 +2,423 @@
 // // mod crate::opengl_macros;
 // use crate::opengl_macros::*;
 
+use std::collections::hash_map::DefaultHasher;
+use std::hash::Hasher;
+use std::io::Cursor;
 use std::path::*;
-use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicUsize, Ordering};
+use std::time::Instant;
 
+use image::GenericImageView;
+
+use super::error::RimError;
+use super::remote::local_file;
+use super::renderer::{self, Renderer, TextureFilter};
+use super::texture_budget::initial_divisor;
+use super::tiles::{TileSet, TileView};
+use super::vec::Vec2;
+
+/// An image with its texture. Images are shared between views and threads with `Arc`, see `ImageCache`,
+/// but the texture must only be changed on the main thread, which has the current renderer.
 pub struct Image {
     pub path: std::path::PathBuf,
     pub renderer_id: usize,
     pub width: usize,
     pub height: usize,
+    /// Images read from stdin or a pipe have no file to reload from.
+    pub in_memory: bool,
+    /// Hash of the encoded file content, changes when the image is reloaded.
+    content_hash: AtomicU64,
+    /// Estimated GPU memory of the texture, counted in `texture_stats`.
+    texture_bytes: AtomicUsize,
+    /// The texture is scaled down by this factor to save memory, 1 is full resolution.
+    divisor: AtomicU32,
+    /// Smallest divisor which fits the image into the maximum texture size.
+    min_divisor: AtomicU32,
+    last_used: Mutex<Instant>,
+    /// Full resolution copy of images which can't be read from their file again, kept once the texture is scaled down,
+    /// and of images too large for a single texture.
+    pixels: Mutex<Option<image::DynamicImage>>,
+    /// Full resolution of images too large for a single texture, the texture only holds a scaled down version.
+    tiles: Mutex<Option<TileSet>>,
+    /// Minification and magnification filter of the texture.
+    filter: Mutex<(TextureFilter, TextureFilter)>,
+    /// Minification and magnification filter of the tiles, which have no mipmaps.
+    tile_filter: Mutex<(TextureFilter, TextureFilter)>,
+    /// Mipmaps are scaled down with a Lanczos filter on the CPU instead of by the driver.
+    lanczos_mipmaps: AtomicBool,
+    /// Shown by a frozen view, so the content must not change anymore.
+    frozen: AtomicBool,
+}
+
+static TEXTURE_COUNT: AtomicUsize = AtomicUsize::new(0);
+static TEXTURE_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+/// Number and estimated memory of all textures of images which are still alive.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TextureStats {
+    pub count: usize,
+    pub bytes: usize,
+}
+
+pub fn texture_stats() -> TextureStats {
+    TextureStats {
+        count: TEXTURE_COUNT.load(Ordering::Relaxed),
+        bytes: TEXTURE_BYTES.load(Ordering::Relaxed),
+    }
+}
+
+pub fn texture_created(bytes: usize) {
+    TEXTURE_COUNT.fetch_add(1, Ordering::Relaxed);
+    TEXTURE_BYTES.fetch_add(bytes, Ordering::Relaxed);
+}
+
+pub fn texture_deleted(bytes: usize) {
+    TEXTURE_COUNT.fetch_sub(1, Ordering::Relaxed);
+    TEXTURE_BYTES.fetch_sub(bytes, Ordering::Relaxed);
 }
 
 impl Image {
-    pub fn new(path: &Path) -> Result<Rc<Image>, String> {
-        let image = match image::open(path) {
-            Ok(img) => img,
-            Err(err) => {
-                println!("{:?}", err);
-                return Err(err.to_string())
-            },
+    /// Decodes an image from encoded bytes, e.g. a png read from stdin.
+    /// `path` is only used to identify the image.
+    pub fn from_memory(path: &Path, data: &[u8]) -> Result<Arc<Image>, RimError> {
+        let image = decode(path, data)?;
+        Image::from_decoded(path, image, hash_content(data), true)
+    }
+
+    /// Creates the texture of an image decoded before, e.g. on a worker thread.
+    pub fn from_decoded(path: &Path, image: image::DynamicImage, content_hash: u64, in_memory: bool) -> Result<Arc<Image>, RimError> {
+        let tex_id = match renderer::with(|renderer| renderer.create_texture()) {
+            Ok(tex_id) => tex_id,
+            Err(_) => return Err(RimError::Gl(format!("Failed to create a texture for {:?}", path))),
         };
+        texture_created(0);
 
-        let img_data = image.to_rgb();
-        let (width, height, format, data_format, data_type) = (
-            img_data.width(),
-            img_data.height(),
-            gl::RGB8,
-            gl::RGB,
-            gl::UNSIGNED_BYTE,
-        );
-
-        let mut tex_id: u32 = 0;
-        GL!(GenTextures(1, &mut tex_id));
-        GL!(BindTexture(TEXTURE_2D, tex_id));
-        GL!(TexParameteri(
-            TEXTURE_2D,
-            TEXTURE_MIN_FILTER,
-            NEAREST as i32
-        ));
-        GL!(TexParameteri(
-            TEXTURE_2D,
-            TEXTURE_MAG_FILTER,
-            NEAREST as i32
-        ));
-        GL!(TexParameteri(TEXTURE_2D, TEXTURE_WRAP_S, REPEAT as i32));
-        GL!(TexParameteri(TEXTURE_2D, TEXTURE_WRAP_T, REPEAT as i32));
-        GL!(TexImage2D(
-            TEXTURE_2D,
-            0,
-            format as i32,
-            width as i32,
-            height as i32,
-            0,
-            data_format,
-            data_type,
-            img_data.as_ptr() as *const std::ffi::c_void
-        ));
-        GL!(BindTexture(TEXTURE_2D, 0));
-
-        let image = Rc::new(Image {
+        let result = Image {
             path: path.to_owned(),
-            renderer_id: tex_id as usize,
-            width: width as usize,
-            height: height as usize,
-        });
+            renderer_id: tex_id,
+            width: image.width() as usize,
+            height: image.height() as usize,
+            in_memory,
+            content_hash: AtomicU64::new(content_hash),
+            texture_bytes: AtomicUsize::new(0),
+            divisor: AtomicU32::new(initial_divisor(image.width(), image.height())),
+            min_divisor: AtomicU32::new(1),
+            last_used: Mutex::new(Instant::now()),
+            pixels: Mutex::new(None),
+            tiles: Mutex::new(None),
+            filter: Mutex::new((TextureFilter::Nearest, TextureFilter::Nearest)),
+            tile_filter: Mutex::new((TextureFilter::Nearest, TextureFilter::Nearest)),
+            lanczos_mipmaps: AtomicBool::new(false),
+            frozen: AtomicBool::new(false),
+        };
+        result.upload_scaled(image)?;
+
+        return Ok(Arc::new(result));
+    }
+
+    /// A copy of the image with its own texture, which can change without changing this one.
+    pub fn duplicate(&self) -> Result<Arc<Image>, RimError> {
+        let (full, content_hash) = match self.pixels.lock().unwrap().as_ref() {
+            Some(pixels) => (pixels.clone(), self.content_hash()),
+            None if self.divisor() == 1 => (self.read_texture(), self.content_hash()),
+            // only the scaled down texture is left of images which can't be read from their file again
+            None if self.in_memory || self.is_frozen() => {
+                let texture = self.read_texture();
+                let full = texture.resize_exact(self.width as u32, self.height as u32, image::imageops::FilterType::Triangle);
+                (full, self.content_hash())
+            },
+            None => decode_file(&self.path)?,
+        };
+
+        let copy = Image::from_decoded(&self.path, full, content_hash, self.in_memory)?;
+        if self.is_frozen() {
+            copy.freeze();
+        }
+        Ok(copy)
+    }
 
-        return Ok(image);
+    /// Marks the image as shown by a frozen view, see `ImageCache::get`.
+    pub fn freeze(&self) {
+        self.frozen.store(true, Ordering::Relaxed);
     }
 
-    pub fn reload_from_disk(&self) -> Result<(), String> {
-        println!("reloading image {:?}", self.path);
-        let image = match image::open(&self.path) {
-            Ok(img) => img,
-            Err(err) => return Err(err.to_string()),
+    pub fn is_frozen(&self) -> bool {
+        self.frozen.load(Ordering::Relaxed)
+    }
+
+    pub fn reload_from_disk(&self) -> Result<(), RimError> {
+        if self.in_memory {
+            return Err(RimError::NotReloadable { path: self.path.clone() });
+        }
+
+        log::debug!("reloading image {:?}", self.path);
+        let (image, hash) = decode_file(&self.path)?;
+        self.content_hash.store(hash, Ordering::Relaxed);
+        self.upload_scaled(image)
+    }
+
+    /// Replaces the content of the texture with a new encoded image, e.g. the next frame of a stream.
+    pub fn reload_from_memory(&self, data: &[u8]) -> Result<(), RimError> {
+        let image = decode(&self.path, data)?;
+        self.content_hash.store(hash_content(data), Ordering::Relaxed);
+        self.upload_scaled(image)
+    }
+
+    pub fn content_hash(&self) -> u64 {
+        self.content_hash.load(Ordering::Relaxed)
+    }
+
+    pub fn divisor(&self) -> u32 {
+        self.divisor.load(Ordering::Relaxed)
+    }
+
+    pub fn min_divisor(&self) -> u32 {
+        self.min_divisor.load(Ordering::Relaxed)
+    }
+
+    /// The image is too large for a single texture and is drawn from tiles when shown larger than the texture.
+    pub fn is_tiled(&self) -> bool {
+        self.tiles.lock().unwrap().is_some()
+    }
+
+    /// Tiles overlapping the region from `min` to `max` in pixels of the image, uploading the ones which are missing.
+    pub fn visible_tiles(&self, min: Vec2, max: Vec2) -> Vec<TileView> {
+        let pixels = self.pixels.lock().unwrap();
+        match (self.tiles.lock().unwrap().as_mut(), pixels.as_ref()) {
+            (Some(tiles), Some(pixels)) => tiles.visible(pixels, min, max, *self.tile_filter.lock().unwrap()),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Sets the minification and magnification filter of the texture and its tiles.
+    pub fn set_filter(&self, min_filter: TextureFilter, mag_filter: TextureFilter) {
+        renderer::with(|renderer| renderer.set_filter(self.renderer_id, min_filter, mag_filter));
+        *self.filter.lock().unwrap() = (min_filter, mag_filter);
+
+        let tile_min_filter = match min_filter {
+            TextureFilter::Nearest => TextureFilter::Nearest,
+            TextureFilter::Linear | TextureFilter::LinearMipmapLinear => TextureFilter::Linear,
         };
+        *self.tile_filter.lock().unwrap() = (tile_min_filter, mag_filter);
+        if let Some(tiles) = self.tiles.lock().unwrap().as_ref() {
+            tiles.set_filter(*self.tile_filter.lock().unwrap());
+        }
+    }
+
+    /// Whether the texture is sampled with these filters and mipmaps, see `set_filter` and `set_lanczos_mipmaps`.
+    pub fn has_filter(&self, min_filter: TextureFilter, mag_filter: TextureFilter, lanczos: bool) -> bool {
+        *self.filter.lock().unwrap() == (min_filter, mag_filter) && self.lanczos_mipmaps.load(Ordering::Relaxed) == lanczos
+    }
+
+    pub fn last_used(&self) -> Instant {
+        *self.last_used.lock().unwrap()
+    }
+
+    /// Marks the image as recently viewed, so its texture is scaled down last.
+    pub fn touch(&self) {
+        *self.last_used.lock().unwrap() = Instant::now();
+    }
+
+    /// Replaces the texture with the image scaled down by `divisor`. Scaling down reads the current texture back,
+    /// going back up to a higher resolution reads the file again. With `keep_full` the full resolution is kept
+    /// in CPU memory instead, for images whose file has changed since or which have no file.
+    pub fn set_divisor(&self, divisor: u32, keep_full: bool) -> Result<(), RimError> {
+        let divisor = std::cmp::max(divisor, self.min_divisor.load(Ordering::Relaxed));
+        let current = self.divisor.load(Ordering::Relaxed);
+        if divisor == current {
+            return Ok(());
+        }
+
+        let keep_full = keep_full || self.in_memory;
+        let (width, height) = scaled_size(self.width as u32, self.height as u32, divisor);
+        let scaled = {
+            let mut pixels = self.pixels.lock().unwrap();
+            if let Some(full) = pixels.as_ref() {
+                full.thumbnail_exact(width, height)
+            } else if divisor > current {
+                let texture = self.read_texture();
+                let scaled = texture.thumbnail_exact(width, height);
+                if keep_full && current == 1 {
+                    *pixels = Some(texture);
+                }
+                scaled
+            } else if keep_full {
+                // the full resolution is gone, the file may have different content by now
+                return Ok(());
+            } else {
+                match image::open(local_file(&self.path)) {
+                    Ok(img) => img.thumbnail_exact(width, height),
+                    Err(err) => return Err(RimError::image(&self.path, err)),
+                }
+            }
+        };
+
+        log::debug!("scaling texture of {:?} down by {}", self.path, divisor);
+        self.divisor.store(divisor, Ordering::Relaxed);
+        self.upload(scaled)
+    }
+
+    /// Uploads a new version of the image with the current divisor. Images larger than the maximum texture size
+    /// are scaled down to fit, and split into tiles for the full resolution.
+    fn upload_scaled(&self, image: image::DynamicImage) -> Result<(), RimError> {
+        let max_size = max_texture_size();
+        let min_divisor = fitting_divisor(image.width(), image.height(), max_size);
+        self.min_divisor.store(min_divisor, Ordering::Relaxed);
+        let divisor = std::cmp::max(self.divisor.load(Ordering::Relaxed), min_divisor);
+        self.divisor.store(divisor, Ordering::Relaxed);
+
+        if divisor == 1 {
+            // the full resolution is read back from the texture when it's scaled down
+            self.tiles.lock().unwrap().take();
+            *self.pixels.lock().unwrap() = None;
+            return self.upload(image);
+        }
+
+        if min_divisor > 1 {
+            log::debug!("{:?} is {}x{}, larger than the maximum texture size {}, using tiles", self.path, image.width(), image.height(), max_size);
+            let tiles = TileSet::new(image.width(), image.height(), max_size);
+            *self.tiles.lock().unwrap() = Some(tiles);
+        } else {
+            self.tiles.lock().unwrap().take();
+        }
+
+        // the full resolution is needed for the tiles, and to go back up for images which can't be read again
+        let (width, height) = scaled_size(image.width(), image.height(), divisor);
+        let scaled = image.thumbnail_exact(width, height);
+        let mut pixels = self.pixels.lock().unwrap();
+        let keep_full = min_divisor > 1 || self.in_memory || pixels.is_some();
+        *pixels = if keep_full { Some(image) } else { None };
+        drop(pixels);
+        self.upload(scaled)
+    }
+
+    /// With `lanczos` the smaller mipmap levels are scaled down from the texture with a Lanczos filter, which keeps
+    /// fine detail sharper than the box filter of most drivers but is slow for large images. Only the first call
+    /// for each mode regenerates them, later uploads keep the mode.
+    pub fn set_lanczos_mipmaps(&self, lanczos: bool) -> Result<(), RimError> {
+        if self.lanczos_mipmaps.swap(lanczos, Ordering::Relaxed) == lanczos {
+            return Ok(());
+        }
+
+        let texture = self.read_texture();
+        self.upload(texture)
+    }
+
+    /// Reads the content of the texture in its current resolution.
+    fn read_texture(&self) -> image::DynamicImage {
+        let pixels = renderer::with(|renderer| renderer.read_texture(self.renderer_id));
+        image::DynamicImage::ImageRgba8(pixels)
+    }
+
+    fn upload(&self, image: image::DynamicImage) -> Result<(), RimError> {
+        check_texture_size(&self.path, image.width(), image.height())?;
 
         let rgba = image.to_rgba();
-        let (img_data, width, height, format, data_format, data_type) = (
-                rgba.as_ref(),
-                rgba.width(),
-                rgba.height(),
-                gl::RGBA8,
-                gl::RGBA,
-                gl::UNSIGNED_BYTE,
-            );
-
-        // let (img_data, width, height, format, data_format, data_type) = match image.as_rgb8() {
-        //     Some(rgb) => (
-        //         rgb.as_ref(),
-        //         rgb.width(),
-        //         rgb.height(),
-        //         gl::RGB8,
-        //         gl::RGB,
-        //         gl::UNSIGNED_BYTE,
-        //     ),
-        //     None => return Err("Failed to load image 2".to_owned()),
-        // };
-
-        GL!(BindTexture(TEXTURE_2D, self.renderer_id as u32));
-        GL!(TexImage2D(
-            TEXTURE_2D,
-            0,
-            format as i32,
-            width as i32,
-            height as i32,
-            0,
-            data_format,
-            data_type,
-            img_data.as_ptr() as *const std::ffi::c_void
-        ));
+        let (width, height) = (rgba.width(), rgba.height());
+        let lanczos = self.lanczos_mipmaps.load(Ordering::Relaxed);
+        renderer::with(|renderer| {
+            renderer.upload_texture(self.renderer_id, 0, &rgba);
+            if lanczos {
+                upload_lanczos_mipmaps(renderer, self.renderer_id, rgba);
+            } else {
+                renderer.generate_mipmaps(self.renderer_id);
+            }
+        });
+
+        let texture_bytes = texture_bytes(width as usize, height as usize, 4);
+        TEXTURE_BYTES.fetch_sub(self.texture_bytes.swap(texture_bytes, Ordering::Relaxed), Ordering::Relaxed);
+        TEXTURE_BYTES.fetch_add(texture_bytes, Ordering::Relaxed);
 
         Ok(())
     }
 }
+
+/// The texture is deleted with the last `Arc` of the image, e.g. when its view is closed.
+/// Images dropped after the renderer, e.g. on exit, have already lost their texture with it.
+impl Drop for Image {
+    fn drop(&mut self) {
+        if renderer::has_current() {
+            renderer::with(|renderer| renderer.delete_texture(self.renderer_id));
+        }
+        texture_deleted(self.texture_bytes.load(Ordering::Relaxed));
+    }
+}
+
+/// Reads and decodes an image file, or the downloaded copy if `path` is a URL, and hashes its content.
+/// Doesn't need the GL context, so it can run on worker threads.
+pub fn decode_file(path: &Path) -> Result<(image::DynamicImage, u64), RimError> {
+    let data = match std::fs::read(local_file(path)) {
+        Ok(data) => data,
+        Err(err) => return Err(RimError::io(path, err)),
+    };
+
+    Ok((decode(path, &data)?, hash_content(&data)))
+}
+
+/// Decodes an encoded image. The format is guessed from the content, and from the extension of `path` if that fails.
+pub fn decode(path: &Path, data: &[u8]) -> Result<image::DynamicImage, RimError> {
+    let mut reader = match image::io::Reader::new(Cursor::new(data)).with_guessed_format() {
+        Ok(reader) => reader,
+        Err(err) => return Err(RimError::io(path, err)),
+    };
+    if reader.format().is_none() {
+        if let Ok(format) = image::ImageFormat::from_path(path) {
+            reader.set_format(format);
+        }
+    }
+
+    match reader.decode() {
+        Ok(image) => Ok(image),
+        Err(err) => Err(RimError::image(path, err)),
+    }
+}
+
+pub fn hash_content(data: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    hasher.write(data);
+    hasher.finish()
+}
+
+/// Estimated memory of a texture with all its mipmaps, which add a third.
+pub fn texture_bytes(width: usize, height: usize, bytes_per_pixel: usize) -> usize {
+    width * height * bytes_per_pixel * 4 / 3
+}
+
+/// Fills the mipmap levels below level 0 of the texture, each scaled down from the one before.
+fn upload_lanczos_mipmaps(renderer: &mut dyn Renderer, texture: usize, mut level_image: image::RgbaImage) {
+    let mut level = 1;
+    while level_image.width() > 1 || level_image.height() > 1 {
+        let (width, height) = scaled_size(level_image.width(), level_image.height(), 2);
+        level_image = image::imageops::resize(&level_image, width, height, image::imageops::FilterType::Lanczos3);
+        renderer.upload_texture(texture, level, &level_image);
+        level += 1;
+    }
+}
+
+fn scaled_size(width: u32, height: u32, divisor: u32) -> (u32, u32) {
+    (std::cmp::max(1, width / divisor), std::cmp::max(1, height / divisor))
+}
+
+fn max_texture_size() -> u32 {
+    renderer::with(|renderer| renderer.max_texture_size())
+}
+
+/// Smallest power of two the image has to be scaled down by to fit into a texture.
+fn fitting_divisor(width: u32, height: u32, max_size: u32) -> u32 {
+    let mut divisor = 1;
+    while width / divisor > max_size || height / divisor > max_size {
+        divisor *= 2;
+    }
+    divisor
+}
+
+/// Images larger than the maximum texture size can't be uploaded, they are scaled down before.
+fn check_texture_size(path: &Path, width: u32, height: u32) -> Result<(), RimError> {
+    let max_size = max_texture_size();
+    if width > max_size || height > max_size {
+        return Err(RimError::Gl(format!("{:?} is {}x{}, larger than the maximum texture size {}x{}", path, width, height, max_size, max_size)));
+    }
+    Ok(())
+}
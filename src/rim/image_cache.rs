@@ -0,0 +1,134 @@
+use std::collections::{HashMap, VecDeque};
+use std::path::*;
+use std::sync::{Arc, Mutex, Weak};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::thread;
+
+use super::error::RimError;
+use super::image::{Image, decode, hash_content};
+use super::remote::local_file;
+use super::wake;
+
+/// Number of threads decoding images in the background.
+const MAX_WORKERS: usize = 4;
+
+struct Decoded {
+    path    : PathBuf,
+    result  : Result<(image::DynamicImage, u64), RimError>,
+}
+
+struct Queue {
+    paths   : VecDeque<PathBuf>,
+    workers : usize,
+}
+
+/// Shares images between views, keyed by their canonical path and the hash of their content, so a file is
+/// only decoded and uploaded once as long as its content doesn't change. A file with new content gets a new image,
+/// e.g. while history keeps the old one in a frozen view. Images of frozen views are never handed out again, their
+/// content must not change with a live view of the file. Images are held weakly and freed with their last view.
+///
+/// Files can be decoded on worker threads, their textures are created on the main thread in `try_recv`.
+pub struct ImageCache {
+    images      : Mutex<HashMap<(PathBuf, u64), Weak<Image>>>,
+    queue       : Arc<Mutex<Queue>>,
+    sender      : Mutex<Sender<Decoded>>,
+    receiver    : Mutex<Receiver<Decoded>>,
+}
+
+impl ImageCache {
+    pub fn new() -> ImageCache {
+        let (sender, receiver) = channel();
+        ImageCache {
+            images      : Mutex::new(HashMap::new()),
+            queue       : Arc::new(Mutex::new(Queue { paths: VecDeque::new(), workers: 0 })),
+            sender      : Mutex::new(sender),
+            receiver    : Mutex::new(receiver),
+        }
+    }
+
+    /// Returns the image of `path` if it is still alive, has the content with this hash and isn't frozen.
+    pub fn get(&self, path: &Path, content_hash: u64) -> Option<Arc<Image>> {
+        let images = self.images.lock().unwrap();
+        images.get(&(path.to_owned(), content_hash))
+            .and_then(Weak::upgrade)
+            .filter(|image| image.content_hash() == content_hash && !image.is_frozen())
+    }
+
+    pub fn insert(&self, image: &Arc<Image>) {
+        let mut images = self.images.lock().unwrap();
+        images.retain(|_, image| image.strong_count() > 0);
+        images.insert((image.path.clone(), image.content_hash()), Arc::downgrade(image));
+    }
+
+    /// Loads an image from a file, or from the downloaded copy if `path` is a URL.
+    /// The file is only decoded if no image with the same path and content is alive.
+    pub fn load(&self, path: &Path) -> Result<Arc<Image>, RimError> {
+        let data = match std::fs::read(local_file(path)) {
+            Ok(data) => data,
+            Err(err) => return Err(RimError::io(path, err)),
+        };
+
+        let hash = hash_content(&data);
+        if let Some(image) = self.get(path, hash) {
+            return Ok(image);
+        }
+
+        let image = Image::from_decoded(path, decode(path, &data)?, hash, false)?;
+        self.insert(&image);
+        Ok(image)
+    }
+
+    /// Drops the files queued with `load_in_background` which no worker has started decoding yet.
+    pub fn cancel_background(&self) {
+        self.queue.lock().unwrap().paths.clear();
+    }
+
+    /// Decodes the file on a worker thread, the image is returned by `try_recv` once it's done.
+    pub fn load_in_background(&self, path: PathBuf) {
+        let mut queue = self.queue.lock().unwrap();
+        queue.paths.push_back(path);
+        if queue.workers >= MAX_WORKERS {
+            return;
+        }
+        queue.workers += 1;
+
+        let shared_queue = self.queue.clone();
+        let sender = self.sender.lock().unwrap().clone();
+        thread::spawn(move || loop {
+            let path = {
+                let mut queue = shared_queue.lock().unwrap();
+                match queue.paths.pop_front() {
+                    Some(path) => path,
+                    None => {
+                        queue.workers -= 1;
+                        break;
+                    },
+                }
+            };
+
+            let result = match std::fs::read(local_file(&path)) {
+                Ok(data) => decode(&path, &data).map(|image| (image, hash_content(&data))),
+                Err(err) => Err(RimError::io(&path, err)),
+            };
+            if sender.send(Decoded { path, result }).is_err() {
+                break;
+            }
+            wake::wake();
+        });
+    }
+
+    /// Returns an image decoded in the background, with its texture created. Must be called on the main thread.
+    pub fn try_recv(&self) -> Option<(PathBuf, Result<Arc<Image>, RimError>)> {
+        let decoded = self.receiver.lock().unwrap().try_recv().ok()?;
+        let result = decoded.result.and_then(|(image, hash)| {
+            if let Some(cached) = self.get(&decoded.path, hash) {
+                return Ok(cached);
+            }
+            let image = Image::from_decoded(&decoded.path, image, hash, false)?;
+            self.insert(&image);
+            Ok(image)
+        });
+
+        Some((decoded.path, result))
+    }
+}
@@ -0,0 +1,412 @@
+//! Remote control of a running instance over a local Unix domain socket.
+//!
+//! The protocol is line based, every line is one command and is answered with
+//! either `ok` or `error <message>` once the command was handled:
+//!
+//! - `open <path>`
+//! - `close [<path>]`
+//! - `reload [<path>]`
+//! - `select <path>`
+//! - `raise`
+//!
+//! Commands without a path apply to the selected image, a path can also be an HTTP(S) URL.
+//! In paths `\\`, `\n`, `\r` and `\t` stand for a backslash, newline, carriage return and tab,
+//! `\s` for a space at the start or end of the path.
+
+use std::io;
+#[cfg(unix)]
+use std::io::{BufRead, BufReader, Write};
+use std::path::*;
+use std::sync::mpsc;
+
+use super::remote::is_url;
+#[cfg(unix)]
+use super::wake;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Open(PathBuf),
+    Close(Option<PathBuf>),
+    Reload(Option<PathBuf>),
+    Select(PathBuf),
+    Raise,
+}
+
+impl Command {
+    pub fn parse(line: &str) -> Result<Command, String> {
+        let line = line.trim();
+        let (name, arg) = match line.find(char::is_whitespace) {
+            Some(index) => (&line[..index], Some(line[index..].trim())),
+            None => (line, None),
+        };
+        let path = match arg.filter(|arg| !arg.is_empty()) {
+            Some(arg) => Some(PathBuf::from(unescape(arg)?)),
+            None => None,
+        };
+
+        match (name, path) {
+            ("open", Some(path)) => Ok(Command::Open(path)),
+            ("close", path) => Ok(Command::Close(path)),
+            ("reload", path) => Ok(Command::Reload(path)),
+            ("select", Some(path)) => Ok(Command::Select(path)),
+            ("raise", None) => Ok(Command::Raise),
+            ("open", None) | ("select", None) => Err(format!("'{}' needs a path", name)),
+            ("", _) => Err("Empty command".to_owned()),
+            _ => Err(format!("Unknown command '{}'", name)),
+        }
+    }
+
+    pub fn to_line(&self) -> String {
+        fn with_path(name: &str, path: &Option<PathBuf>) -> String {
+            match path {
+                Some(path) => format!("{} {}", name, escape(path)),
+                None => name.to_owned(),
+            }
+        }
+
+        match self {
+            Command::Open(path) => format!("open {}", escape(path)),
+            Command::Close(path) => with_path("close", path),
+            Command::Reload(path) => with_path("reload", path),
+            Command::Select(path) => format!("select {}", escape(path)),
+            Command::Raise => "raise".to_owned(),
+        }
+    }
+
+    /// Makes the path of the command absolute relative to the current directory,
+    /// so the receiving instance doesn't depend on the working directory of the sender.
+    pub fn into_absolute(self) -> Command {
+        fn absolute(path: PathBuf) -> PathBuf {
+            if path.is_absolute() || path.to_str().is_some_and(is_url) {
+                return path;
+            }
+            match std::env::current_dir() {
+                Ok(dir) => dir.join(path),
+                Err(_) => path,
+            }
+        }
+
+        match self {
+            Command::Open(path) => Command::Open(absolute(path)),
+            Command::Close(path) => Command::Close(path.map(absolute)),
+            Command::Reload(path) => Command::Reload(path.map(absolute)),
+            Command::Select(path) => Command::Select(absolute(path)),
+            Command::Raise => Command::Raise,
+        }
+    }
+}
+
+/// Writes `path` so it fits on one line and is read back unchanged by `unescape`.
+fn escape(path: &Path) -> String {
+    let path = path.to_string_lossy();
+    let last = path.chars().count().saturating_sub(1);
+    let mut escaped = String::with_capacity(path.len());
+    for (index, c) in path.chars().enumerate() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            // the argument is trimmed when parsed
+            ' ' if index == 0 || index == last => escaped.push_str("\\s"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn unescape(arg: &str) -> Result<String, String> {
+    let mut unescaped = String::with_capacity(arg.len());
+    let mut chars = arg.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('\\') => unescaped.push('\\'),
+            Some('n') => unescaped.push('\n'),
+            Some('r') => unescaped.push('\r'),
+            Some('t') => unescaped.push('\t'),
+            Some('s') => unescaped.push(' '),
+            Some(c) => return Err(format!("Unknown escape '\\{}' in '{}'", c, arg)),
+            None => return Err(format!("'{}' ends with a backslash", arg)),
+        }
+    }
+    Ok(unescaped)
+}
+
+/// `$XDG_RUNTIME_DIR/rim.sock` if set, otherwise a socket in a per-user directory in the temp directory.
+/// The directory is created only accessible by the user, so no other user can listen in their place.
+/// An existing one which others can access, e.g. created by another user, is an error.
+pub fn default_socket_path() -> io::Result<PathBuf> {
+    if let Some(dir) = std::env::var_os("XDG_RUNTIME_DIR").filter(|dir| !dir.is_empty()) {
+        return Ok(PathBuf::from(dir).join("rim.sock"));
+    }
+
+    let dir = user_temp_dir("rim");
+    create_private_dir(&dir)?;
+    Ok(dir.join("rim.sock"))
+}
+
+/// `<temp dir>/<name>-$USER`, a directory of the user in the temp directory shared with other users.
+pub(super) fn user_temp_dir(name: &str) -> PathBuf {
+    let user = std::env::var("USER").unwrap_or_else(|_| "default".to_owned());
+    std::env::temp_dir().join(format!("{}-{}", name, user))
+}
+
+/// Creates `dir` only accessible by the user, an existing one which others can access is an error.
+#[cfg(unix)]
+pub(super) fn create_private_dir(dir: &Path) -> io::Result<()> {
+    use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+
+    match std::fs::DirBuilder::new().mode(0o700).create(dir) {
+        Ok(_) => {},
+        Err(ref err) if err.kind() == io::ErrorKind::AlreadyExists => {},
+        Err(err) => return Err(err),
+    }
+
+    // a directory of another user can't be entered when it's private, so this is enough to
+    // not hand out paths to them
+    let metadata = std::fs::symlink_metadata(dir)?;
+    if !metadata.is_dir() || metadata.permissions().mode() & 0o077 != 0 {
+        return Err(io::Error::new(io::ErrorKind::PermissionDenied, format!("{:?} is not a private directory", dir)));
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub(super) fn create_private_dir(dir: &Path) -> io::Result<()> {
+    std::fs::create_dir_all(dir)
+}
+
+pub struct IpcServer {
+    path    : PathBuf,
+    receiver: mpsc::Receiver<Request>,
+}
+
+/// A command received by the server, the client waits for its answer.
+pub struct Request {
+    pub command : Command,
+    reply       : mpsc::Sender<Result<(), String>>,
+}
+
+impl Request {
+    pub fn answer(self, result: Result<(), String>) {
+        // the client may be gone already
+        self.reply.send(result).unwrap_or(());
+    }
+}
+
+impl IpcServer {
+    /// Listens on `path` on a background thread. A socket file left behind by an instance
+    /// that didn't shut down cleanly is replaced, a socket of a running instance is not.
+    #[cfg(unix)]
+    pub fn start(path: &Path) -> io::Result<IpcServer> {
+        use std::os::unix::net::{UnixListener, UnixStream};
+
+        let listener = match UnixListener::bind(path) {
+            Ok(listener) => listener,
+            Err(ref err) if err.kind() == io::ErrorKind::AddrInUse => {
+                if UnixStream::connect(path).is_ok() {
+                    return Err(io::Error::new(io::ErrorKind::AddrInUse, format!("Another instance is listening on {:?}", path)));
+                }
+                std::fs::remove_file(path)?;
+                UnixListener::bind(path)?
+            },
+            Err(err) => return Err(err),
+        };
+
+        let (sender, receiver) = mpsc::channel();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                match stream {
+                    // every client has its own thread, so one which doesn't finish its line doesn't block others
+                    Ok(stream) => {
+                        let sender = sender.clone();
+                        std::thread::spawn(move || handle_client(stream, &sender));
+                    },
+                    Err(err) => log::warn!("IPC connection failed: {}", err),
+                }
+            }
+        });
+
+        Ok(IpcServer {
+            path    : path.to_owned(),
+            receiver,
+        })
+    }
+
+    #[cfg(not(unix))]
+    pub fn start(_path: &Path) -> io::Result<IpcServer> {
+        Err(io::Error::new(io::ErrorKind::Other, "Remote control is only supported on Unix"))
+    }
+
+    /// Returns the next command, its client waits until the request is answered.
+    pub fn try_recv(&self) -> Option<Request> {
+        self.receiver.try_recv().ok()
+    }
+}
+
+impl Drop for IpcServer {
+    fn drop(&mut self) {
+        std::fs::remove_file(&self.path).unwrap_or(());
+    }
+}
+
+#[cfg(unix)]
+fn handle_client(stream: std::os::unix::net::UnixStream, sender: &mpsc::Sender<Request>) {
+    let mut writer = match stream.try_clone() {
+        Ok(writer) => writer,
+        Err(_) => return,
+    };
+
+    for line in BufReader::new(stream).lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(_) => break,
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let result = match Command::parse(&line) {
+            Ok(command) => {
+                let (reply, answer) = mpsc::channel();
+                if sender.send(Request { command, reply }).is_err() {
+                    break;
+                }
+                wake::wake();
+                match answer.recv() {
+                    Ok(result) => result,
+                    // the app quit without handling the command
+                    Err(_) => break,
+                }
+            },
+            Err(msg) => Err(msg),
+        };
+        let reply = match result {
+            Ok(_) => "ok".to_owned(),
+            // the reply has to stay on one line
+            Err(msg) => format!("error {}", msg.replace(['\r', '\n'], " ")),
+        };
+        if writeln!(writer, "{}", reply).is_err() {
+            break;
+        }
+    }
+}
+
+/// Sends `commands` to the instance listening on `path`.
+/// Fails if no instance is listening, errors reported by the instance are returned per command.
+#[cfg(unix)]
+pub fn send_commands(path: &Path, commands: &[Command]) -> io::Result<Vec<Result<(), String>>> {
+    use std::os::unix::net::UnixStream;
+
+    let mut stream = UnixStream::connect(path)?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+
+    let mut results = Vec::new();
+    for command in commands.iter() {
+        writeln!(stream, "{}", command.to_line())?;
+
+        let mut reply = String::new();
+        reader.read_line(&mut reply)?;
+        let reply = reply.trim();
+        results.push(if reply == "ok" {
+            Ok(())
+        } else {
+            Err(reply.trim_start_matches("error").trim().to_owned())
+        });
+    }
+
+    Ok(results)
+}
+
+#[cfg(not(unix))]
+pub fn send_commands(_path: &Path, _commands: &[Command]) -> io::Result<Vec<Result<(), String>>> {
+    Err(io::Error::new(io::ErrorKind::Other, "Remote control is only supported on Unix"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(command: Command) {
+        let line = command.to_line();
+        assert!(!line.contains('\n') && !line.contains('\r'), "{:?}", line);
+        assert_eq!(Command::parse(&line), Ok(command));
+    }
+
+    #[test]
+    fn commands_round_trip() {
+        round_trip(Command::Open(PathBuf::from("/images/a.png")));
+        round_trip(Command::Close(None));
+        round_trip(Command::Close(Some(PathBuf::from("/images/a.png"))));
+        round_trip(Command::Reload(None));
+        round_trip(Command::Select(PathBuf::from("https://example.com/a.png")));
+        round_trip(Command::Raise);
+    }
+
+    #[test]
+    fn paths_with_spaces_round_trip() {
+        round_trip(Command::Open(PathBuf::from("/my images/a b.png")));
+        round_trip(Command::Open(PathBuf::from(" leading and trailing ")));
+        round_trip(Command::Reload(Some(PathBuf::from(" "))));
+    }
+
+    #[test]
+    fn paths_with_control_characters_round_trip() {
+        round_trip(Command::Open(PathBuf::from("/images/line\nbreak.png")));
+        round_trip(Command::Select(PathBuf::from("/images/\r\t.png")));
+        round_trip(Command::Close(Some(PathBuf::from("/images/back\\slash\\n.png"))));
+    }
+
+    #[test]
+    fn parse_typed_commands() {
+        assert_eq!(Command::parse("  open   /images/a b.png \r"), Ok(Command::Open(PathBuf::from("/images/a b.png"))));
+        assert_eq!(Command::parse("close"), Ok(Command::Close(None)));
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(Command::parse("").is_err());
+        assert!(Command::parse("open").is_err());
+        assert!(Command::parse("select  ").is_err());
+        assert!(Command::parse("raise now").is_err());
+        assert!(Command::parse("quit").is_err());
+        assert!(Command::parse("open a\\x").is_err());
+        assert!(Command::parse("open a\\").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn clients_get_the_result_of_their_commands() {
+        let dir = std::env::temp_dir().join(format!("rim-test-ipc-{}", std::process::id()));
+        create_private_dir(&dir).unwrap();
+        let path = dir.join("rim.sock");
+        let server = IpcServer::start(&path).unwrap();
+
+        // a client which never finishes its line doesn't block the others
+        let mut idle = std::os::unix::net::UnixStream::connect(&path).unwrap();
+        write!(idle, "open /images/a.png").unwrap();
+
+        let client_path = path.clone();
+        let client = std::thread::spawn(move || send_commands(&client_path, &[Command::Raise, Command::Close(None)]).unwrap());
+
+        let answers = vec![(Command::Raise, Ok(())), (Command::Close(None), Err("No image\nis open".to_owned()))];
+        for (command, result) in answers.into_iter() {
+            let request = loop {
+                match server.try_recv() {
+                    Some(request) => break request,
+                    None => std::thread::sleep(std::time::Duration::from_millis(1)),
+                }
+            };
+            assert_eq!(request.command, command);
+            request.answer(result);
+        }
+        assert_eq!(client.join().unwrap(), vec![Ok(()), Err("No image is open".to_owned())]);
+
+        drop(server);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
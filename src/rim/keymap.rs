@@ -0,0 +1,282 @@
+use std::fmt;
+
+use sdl2::keyboard::{Mod, Scancode};
+
+use super::action::Action;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub struct Modifiers {
+    pub ctrl    : bool,
+    pub shift   : bool,
+    pub alt     : bool,
+}
+
+impl Modifiers {
+    pub fn none() -> Modifiers {
+        Modifiers { ctrl: false, shift: false, alt: false }
+    }
+
+    pub fn from_keymod(keymod: Mod) -> Modifiers {
+        Modifiers {
+            ctrl    : keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD),
+            shift   : keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD),
+            alt     : keymod.intersects(Mod::LALTMOD | Mod::RALTMOD),
+        }
+    }
+
+    pub fn from_ui(ui: &imgui::Ui) -> Modifiers {
+        Modifiers {
+            ctrl    : ui.is_key_down(Scancode::LCtrl as u32) || ui.is_key_down(Scancode::RCtrl as u32),
+            shift   : ui.is_key_down(Scancode::LShift as u32) || ui.is_key_down(Scancode::RShift as u32),
+            alt     : ui.is_key_down(Scancode::LAlt as u32) || ui.is_key_down(Scancode::RAlt as u32),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct KeyBinding {
+    pub scancode    : Scancode,
+    pub modifiers   : Modifiers,
+}
+
+impl KeyBinding {
+    pub fn new(scancode: Scancode) -> KeyBinding {
+        KeyBinding { scancode, modifiers: Modifiers::none() }
+    }
+
+    pub fn ctrl(mut self) -> KeyBinding {
+        self.modifiers.ctrl = true;
+        self
+    }
+
+    pub fn shift(mut self) -> KeyBinding {
+        self.modifiers.shift = true;
+        self
+    }
+
+    /// Parses bindings like `Ctrl+Shift+Up`, `F5` or `.`.
+    /// The key name is the SDL scancode name, modifiers are case insensitive.
+    pub fn parse(text: &str) -> Result<KeyBinding, String> {
+        let mut modifiers = Modifiers::none();
+        let mut key = None;
+
+        for part in text.split('+').map(str::trim) {
+            if key.is_some() {
+                return Err(format!("Key binding '{}': modifiers must come before the key", text));
+            }
+
+            match part.to_lowercase().as_str() {
+                "ctrl" | "control" => modifiers.ctrl = true,
+                "shift" => modifiers.shift = true,
+                "alt" => modifiers.alt = true,
+                "" => return Err(format!("Key binding '{}' is empty or has a trailing '+'", text)),
+                _ => key = Some(part),
+            }
+        }
+
+        match key {
+            Some(key) => match Scancode::from_name(key) {
+                Some(scancode) => Ok(KeyBinding { scancode, modifiers }),
+                None => Err(format!("Key binding '{}': unknown key '{}'", text, key)),
+            },
+            None => Err(format!("Key binding '{}' has no key", text)),
+        }
+    }
+
+    pub fn matches(&self, scancode: Scancode, modifiers: Modifiers) -> bool {
+        self.scancode == scancode && self.modifiers == modifiers
+    }
+}
+
+impl fmt::Display for KeyBinding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.modifiers.ctrl {
+            write!(f, "Ctrl+")?;
+        }
+        if self.modifiers.shift {
+            write!(f, "Shift+")?;
+        }
+        if self.modifiers.alt {
+            write!(f, "Alt+")?;
+        }
+        write!(f, "{}", self.scancode.name())
+    }
+}
+
+/// Maps key bindings to actions. An action can have any number of bindings,
+/// but every binding triggers at most one action.
+pub struct KeyMap {
+    bindings: Vec<(KeyBinding, Action)>,
+}
+
+impl KeyMap {
+    pub fn empty() -> KeyMap {
+        KeyMap { bindings: Vec::new() }
+    }
+
+    /// Binds `binding` to `action`, replacing whatever action was bound to it before.
+    pub fn bind(&mut self, binding: KeyBinding, action: Action) {
+        self.bindings.retain(|(b, _)| *b != binding);
+        self.bindings.push((binding, action));
+    }
+
+    pub fn unbind_action(&mut self, action: Action) {
+        self.bindings.retain(|(_, a)| *a != action);
+    }
+
+    pub fn action_for(&self, scancode: Scancode, keymod: Mod) -> Option<Action> {
+        let modifiers = Modifiers::from_keymod(keymod);
+        self.bindings.iter()
+            .find(|(binding, _)| binding.matches(scancode, modifiers))
+            .map(|(_, action)| *action)
+    }
+
+    pub fn bindings_for(&self, action: Action) -> Vec<KeyBinding> {
+        self.bindings.iter()
+            .filter(|(_, a)| *a == action)
+            .map(|(binding, _)| *binding)
+            .collect()
+    }
+
+    /// Returns the bindings of `action` formatted for display, e.g. `Ctrl+R, F5`.
+    pub fn bindings_text(&self, action: Action) -> String {
+        self.bindings_for(action).iter()
+            .map(|binding| binding.to_string())
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// Checks whether any binding of `action` is currently held down.
+    pub fn is_held(&self, ui: &imgui::Ui, action: Action) -> bool {
+        let modifiers = Modifiers::from_ui(ui);
+        self.bindings.iter().any(|(binding, a)| {
+            *a == action && binding.modifiers == modifiers && ui.is_key_down(binding.scancode as u32)
+        })
+    }
+
+    pub fn print_bindings(&self) {
+        for action in Action::ALL.iter() {
+            println!("{:20} {:28} {}", action.name(), self.bindings_text(*action), action.description());
+        }
+    }
+}
+
+impl Default for KeyMap {
+    fn default() -> KeyMap {
+        use Scancode::*;
+        let key = KeyBinding::new;
+
+        let mut keymap = KeyMap::empty();
+        keymap.bind(key(P).ctrl(), Action::Quit);
+        keymap.bind(key(O).ctrl(), Action::OpenFile);
+        keymap.bind(key(W).ctrl(), Action::CloseView);
+        keymap.bind(key(M).ctrl(), Action::ToggleFullscreen);
+        keymap.bind(key(R).ctrl(), Action::Reload);
+        keymap.bind(key(F5), Action::Reload);
+        keymap.bind(key(P).ctrl().shift(), Action::CommandPalette);
+        keymap.bind(key(Escape), Action::DismissNotifications);
+        keymap.bind(key(L).ctrl().shift(), Action::ToggleNotificationLog);
+        keymap.bind(key(F12), Action::ToggleLog);
+        keymap.bind(key(F3), Action::ToggleDebugOverlay);
+
+        keymap.bind(key(N).ctrl(), Action::FilterNearest);
+        keymap.bind(key(L).ctrl(), Action::FilterLinear);
+        keymap.bind(key(T).ctrl(), Action::FilterTrilinear);
+        keymap.bind(key(T).ctrl().shift(), Action::FilterLanczos);
+
+        keymap.bind(key(A).ctrl(), Action::LayoutAuto);
+        keymap.bind(key(H).ctrl(), Action::LayoutHorizontal);
+        keymap.bind(key(V).ctrl(), Action::LayoutVertical);
+        keymap.bind(key(Num1).ctrl(), Action::LayoutGrid);
+        keymap.bind(key(Num2).ctrl(), Action::LayoutRows);
+        keymap.bind(key(Num3).ctrl(), Action::LayoutMasonry);
+        keymap.bind(key(Num4).ctrl(), Action::LayoutFocus);
+        keymap.bind(key(Num5).ctrl(), Action::LayoutSplit);
+
+        keymap.bind(key(H).ctrl().shift(), Action::SplitHorizontal);
+        keymap.bind(key(V).ctrl().shift(), Action::SplitVertical);
+        keymap.bind(key(M).ctrl().shift(), Action::MergePanes);
+        keymap.bind(key(Period).ctrl().shift(), Action::GrowPane);
+        keymap.bind(key(Comma).ctrl().shift(), Action::ShrinkPane);
+
+        keymap.bind(key(PageDown).ctrl(), Action::NextGroup);
+        keymap.bind(key(PageUp).ctrl(), Action::PreviousGroup);
+        keymap.bind(key(N).ctrl().shift(), Action::NewGroup);
+        keymap.bind(key(PageDown).ctrl().shift(), Action::MoveToNextGroup);
+        keymap.bind(key(PageUp).ctrl().shift(), Action::MoveToPreviousGroup);
+
+        keymap.bind(key(I), Action::SelectUp);
+        keymap.bind(key(K), Action::SelectDown);
+        keymap.bind(key(J), Action::SelectLeft);
+        keymap.bind(key(L), Action::SelectRight);
+        keymap.bind(key(Up).ctrl(), Action::SelectUp);
+        keymap.bind(key(Down).ctrl(), Action::SelectDown);
+        keymap.bind(key(Left).ctrl(), Action::SelectLeft);
+        keymap.bind(key(Right).ctrl(), Action::SelectRight);
+
+        keymap.bind(key(I).shift(), Action::MoveUp);
+        keymap.bind(key(K).shift(), Action::MoveDown);
+        keymap.bind(key(J).shift(), Action::MoveLeft);
+        keymap.bind(key(L).shift(), Action::MoveRight);
+        keymap.bind(key(Up).ctrl().shift(), Action::MoveUp);
+        keymap.bind(key(Down).ctrl().shift(), Action::MoveDown);
+        keymap.bind(key(Left).ctrl().shift(), Action::MoveLeft);
+        keymap.bind(key(Right).ctrl().shift(), Action::MoveRight);
+
+        keymap.bind(key(W), Action::PanUp);
+        keymap.bind(key(S), Action::PanDown);
+        keymap.bind(key(A), Action::PanLeft);
+        keymap.bind(key(D), Action::PanRight);
+        keymap.bind(key(Up), Action::PanUp);
+        keymap.bind(key(Down), Action::PanDown);
+        keymap.bind(key(Left), Action::PanLeft);
+        keymap.bind(key(Right), Action::PanRight);
+
+        keymap.bind(key(Period), Action::ZoomIn);
+        keymap.bind(key(Comma), Action::ZoomOut);
+        keymap.bind(key(Up).shift(), Action::ZoomIn);
+        keymap.bind(key(Down).shift(), Action::ZoomOut);
+        keymap.bind(key(Space), Action::ResetZoom);
+
+        keymap
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_modifiers_and_key() {
+        let binding = KeyBinding::parse("ctrl + Shift+Up").unwrap();
+        assert_eq!(binding, KeyBinding::new(Scancode::Up).ctrl().shift());
+        assert_eq!(KeyBinding::parse("F5").unwrap(), KeyBinding::new(Scancode::F5));
+        assert_eq!(binding.to_string(), "Ctrl+Shift+Up");
+    }
+
+    #[test]
+    fn parse_trailing_plus() {
+        assert!(KeyBinding::parse("Ctrl+").is_err());
+        assert!(KeyBinding::parse("").is_err());
+    }
+
+    #[test]
+    fn parse_modifier_after_key() {
+        assert!(KeyBinding::parse("R+Ctrl").is_err());
+    }
+
+    #[test]
+    fn parse_unknown_key_or_modifier() {
+        assert!(KeyBinding::parse("Ctrl+NoSuchKey").is_err());
+        assert!(KeyBinding::parse("Super+R").is_err());
+    }
+
+    #[test]
+    fn bind_replaces_action_of_binding() {
+        let mut keymap = KeyMap::empty();
+        keymap.bind(KeyBinding::new(Scancode::F5), Action::Reload);
+        keymap.bind(KeyBinding::new(Scancode::F5), Action::Quit);
+        assert!(keymap.bindings_for(Action::Reload).is_empty());
+        assert_eq!(keymap.bindings_for(Action::Quit), [KeyBinding::new(Scancode::F5)]);
+    }
+}
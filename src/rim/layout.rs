@@ -1,17 +1,198 @@
 use std::boxed::Box;
+use std::cmp::Ordering;
+use std::ops::{Range, RangeInclusive};
 
+use serde::{Deserialize, Serialize};
+
+use super::split_layout::SplitLayout;
 use super::view::View;
 
-#[derive(Debug,Copy, Clone)]
+#[derive(Debug,Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum LayoutDirection {
     Horizontal,
     Vertical,
 }
+
+/// The layouts which can be chosen at runtime, stored in sessions by name.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LayoutKind {
+    #[default]
+    Grid,
+    Rows,
+    Masonry,
+    Focus,
+    /// Panes arranged by the user, see `SplitLayout`.
+    Split,
+}
+
+impl LayoutKind {
+    pub fn create(self) -> Box<dyn Layout> {
+        match self {
+            LayoutKind::Grid => GridLayout::new(),
+            LayoutKind::Rows => RowLayout::new(),
+            LayoutKind::Masonry => MasonryLayout::new(),
+            LayoutKind::Focus => FocusLayout::new(),
+            LayoutKind::Split => SplitLayout::new(),
+        }
+    }
+}
+
+/// What moving the selection past the last view of a row or column does.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EdgeBehavior {
+    /// Continue with the view at the other end of the row or column.
+    #[default]
+    Wrap,
+    /// Keep the view selected.
+    Stop,
+}
+
+/// Cell of a view in window coordinates.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct Rect {
+    pub x       : i32,
+    pub y       : i32,
+    pub width   : i32,
+    pub height  : i32,
+}
+
+impl Rect {
+    /// Swaps x and y, used to get the vertical variant of a layout from the horizontal one and the other way around.
+    fn transposed(self) -> Rect {
+        Rect {
+            x       : self.y,
+            y       : self.x,
+            width   : self.height,
+            height  : self.width,
+        }
+    }
+
+    /// Start and size along the x axis, or the y axis if `vertical`.
+    pub(super) fn span(&self, vertical: bool) -> (i32, i32) {
+        if vertical {
+            (self.y, self.height)
+        } else {
+            (self.x, self.width)
+        }
+    }
+}
+
 pub trait Layout {
-    fn layout(&self, views: &mut [View], width: i32, height: i32, direction: LayoutDirection);
-    fn get_next_index(&self, view_count: usize, index: usize, x_off: i32, y_iff: i32, direction: LayoutDirection) -> usize;
+    /// Returns the cell of every view, in the order of `aspect_ratios` (width / height of each image).
+    /// `selected` is the index of the selected view.
+    fn layout(&self, aspect_ratios: &[f32], selected: usize, width: i32, height: i32, direction: LayoutDirection) -> Vec<Rect>;
+
+    /// Index of the view next to the one at `index` in the direction of `x_off` and `y_off`, in the `cells`
+    /// returned by `layout`. Only views in the same row or column are neighbors, the closest one is chosen.
+    fn get_next_index(&self, cells: &[Rect], index: usize, x_off: i32, y_off: i32, edges: EdgeBehavior) -> usize {
+        let index = if x_off != 0 { step(cells, index, false, x_off.signum(), edges) } else { index };
+        if y_off != 0 { step(cells, index, true, y_off.signum(), edges) } else { index }
+    }
 }
 
+/// Steps from the view at `index` to the closest view in the same row (or column if `vertical`) in the direction
+/// of `sign`. Views share a row if their cells overlap vertically, which also works for rows of different heights.
+fn step(cells: &[Rect], index: usize, vertical: bool, sign: i32, edges: EdgeBehavior) -> usize {
+    let current = match cells.get(index) {
+        Some(current) => current,
+        None => return index,
+    };
+    let (start, size) = current.span(vertical);
+    let (cross_start, cross_size) = current.span(!vertical);
+
+    // distances between the centers, doubled to stay whole numbers,
+    // along the step (negative for views behind) and across it
+    let in_line: Vec<(usize, i32, i32)> = cells.iter()
+        .enumerate()
+        .filter(|(other, cell)| {
+            let (other_start, other_size) = cell.span(!vertical);
+            *other != index && other_start < cross_start + cross_size && cross_start < other_start + other_size
+        })
+        .map(|(other, cell)| {
+            let (other_start, other_size) = cell.span(vertical);
+            let (other_cross_start, other_cross_size) = cell.span(!vertical);
+            let distance = (2 * other_start + other_size - 2 * start - size) * sign;
+            let cross_distance = (2 * other_cross_start + other_cross_size - 2 * cross_start - cross_size).abs();
+            (other, distance, cross_distance)
+        })
+        .collect();
+
+    let ahead = in_line.iter()
+        .filter(|(_, distance, _)| *distance > 0)
+        .min_by_key(|(_, distance, cross_distance)| (*distance, *cross_distance));
+
+    let next = match (ahead, edges) {
+        (Some(next), _) => Some(next),
+        // the view farthest behind is at the other end
+        (None, EdgeBehavior::Wrap) => in_line.iter()
+            .filter(|(_, distance, _)| *distance < 0)
+            .min_by_key(|(_, distance, cross_distance)| (*distance, *cross_distance)),
+        (None, EdgeBehavior::Stop) => None,
+    };
+
+    next.map_or(index, |(other, _, _)| *other)
+}
+
+/// Returns the cells of the views in `layout`.
+pub fn cells(layout: &dyn Layout, views: &[View], selected: usize, width: i32, height: i32, direction: LayoutDirection) -> Vec<Rect> {
+    let aspect_ratios: Vec<f32> = views.iter()
+        .map(|view| view.image.width.max(1) as f32 / view.image.height.max(1) as f32)
+        .collect();
+
+    layout.layout(&aspect_ratios, selected, width.max(1), height.max(1), direction)
+}
+
+/// Moves and resizes the views into their cells of `layout`.
+pub fn apply(layout: &dyn Layout, views: &mut [View], selected: usize, width: i32, height: i32, direction: LayoutDirection) {
+    let cells = cells(layout, views, selected, width, height, direction);
+    for (view, cell) in views.iter_mut().zip(cells) {
+        view.x = cell.x;
+        view.y = cell.y;
+        view.width = cell.width;
+        view.height = cell.height;
+    }
+}
+
+/// Splits `length` into parts proportional to `weights`, returned as offset and size.
+/// The parts are rounded so they add up to exactly `length`.
+pub(super) fn split(length: i32, weights: &[f32]) -> Vec<(i32, i32)> {
+    let total: f32 = weights.iter().sum();
+    let mut sum = 0.0;
+    let mut start = 0;
+    weights.iter()
+        .map(|weight| {
+            sum += weight;
+            let end = (sum / total * length as f32).round() as i32;
+            let part = (start, end - start);
+            start = end;
+            part
+        })
+        .collect()
+}
+
+/// How far the views would have to be stretched or squeezed for something of `natural` length to fill `length`.
+fn distortion(natural: f32, length: i32) -> f32 {
+    (natural / length as f32).ln().abs()
+}
+
+/// Row or column counts worth trying, a window around the `expected` count which fits the window best.
+/// Trying every count would take quadratic time in the number of views, masonry even cubic.
+fn candidate_counts(expected: f32, view_count: usize) -> RangeInclusive<usize> {
+    let window = (view_count as f32).sqrt().ceil() as usize;
+    let expected = expected.round().max(1.0).min(view_count as f32) as usize;
+    expected.saturating_sub(window).max(1) ..= (expected + window).min(view_count)
+}
+
+fn least_distorted<T>(candidates: impl Iterator<Item = (T, f32)>) -> Option<T> {
+    candidates
+        .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(Ordering::Equal))
+        .map(|(candidate, _)| candidate)
+}
+
+/// Divides the window into cells of the same size, ignoring the aspect ratios.
 pub struct GridLayout {}
 
 impl GridLayout {
@@ -53,36 +234,528 @@ impl GridLayout {
 
 impl Layout for GridLayout {
 
-    fn layout(&self, views: &mut [View], width: i32, height: i32, direction: LayoutDirection) {
-        let (grid_columns, grid_rows) = self.get_grid_size(views.len() as i32, direction);
+    fn layout(&self, aspect_ratios: &[f32], _selected: usize, width: i32, height: i32, direction: LayoutDirection) -> Vec<Rect> {
+        if aspect_ratios.is_empty() {
+            return Vec::new();
+        }
+
+        let (grid_columns, grid_rows) = self.get_grid_size(aspect_ratios.len() as i32, direction);
 
         let cell_width = width / grid_columns;
         let cell_height = height / grid_rows;
 
-        for y in 0 .. grid_rows {
-            for x in 0 .. grid_columns {
-                let index = (x + y * grid_columns) as usize;
-                if index >= views.len() {
-                    continue;
+        (0 .. aspect_ratios.len() as i32)
+            .map(|index| Rect {
+                x       : index % grid_columns * cell_width,
+                y       : index / grid_columns * cell_height,
+                width   : cell_width,
+                height  : cell_height,
+            })
+            .collect()
+    }
+}
+
+/// Packs the views in order into rows (horizontal) or columns (vertical) which each fill the window.
+/// Cells keep about the aspect ratio of their image, the number of rows is the one which fits the window best.
+pub struct RowLayout {}
+
+impl RowLayout {
+    pub fn new() -> Box<dyn Layout> {
+        Box::new(RowLayout {})
+    }
+}
+
+impl Layout for RowLayout {
+    fn layout(&self, aspect_ratios: &[f32], _selected: usize, width: i32, height: i32, direction: LayoutDirection) -> Vec<Rect> {
+        match direction {
+            LayoutDirection::Horizontal => justify(aspect_ratios, width, height),
+            LayoutDirection::Vertical => {
+                let inverted: Vec<f32> = aspect_ratios.iter().map(|aspect| 1.0 / aspect).collect();
+                justify(&inverted, height, width).into_iter().map(Rect::transposed).collect()
+            },
+        }
+    }
+}
+
+fn justify(aspect_ratios: &[f32], width: i32, height: i32) -> Vec<Rect> {
+    // n rows with the same sum of aspect ratios are n² * width / total high
+    let total: f32 = aspect_ratios.iter().sum();
+    let expected = (total * height as f32 / width as f32).sqrt();
+    let candidates = candidate_counts(expected, aspect_ratios.len()).map(|row_count| {
+        let rows = partition(aspect_ratios, row_count);
+        // height of the rows if every cell had the aspect ratio of its image
+        let natural: f32 = rows.iter()
+            .map(|row| width as f32 / aspect_ratios[row.clone()].iter().sum::<f32>())
+            .sum();
+        (rows, distortion(natural, height))
+    });
+    let rows = match least_distorted(candidates) {
+        Some(rows) => rows,
+        None => return Vec::new(),
+    };
+
+    let row_heights: Vec<f32> = rows.iter()
+        .map(|row| 1.0 / aspect_ratios[row.clone()].iter().sum::<f32>())
+        .collect();
+
+    let mut cells = Vec::with_capacity(aspect_ratios.len());
+    for (row, (y, row_height)) in rows.iter().zip(split(height, &row_heights)) {
+        for (x, cell_width) in split(width, &aspect_ratios[row.clone()]) {
+            cells.push(Rect { x, y, width: cell_width, height: row_height });
+        }
+    }
+    cells
+}
+
+/// Splits the views in order into `row_count` rows, none of them empty, with about the same sum of aspect ratios.
+fn partition(aspect_ratios: &[f32], row_count: usize) -> Vec<Range<usize>> {
+    let total: f32 = aspect_ratios.iter().sum();
+    let mut rows = Vec::with_capacity(row_count);
+    let mut start = 0;
+    let mut sum = 0.0;
+
+    for (index, aspect) in aspect_ratios.iter().enumerate() {
+        // every row after the current one needs at least one view
+        let rows_after = row_count - rows.len() - 1;
+        let row_end = total * (rows.len() + 1) as f32 / row_count as f32;
+        let past_end = sum + aspect / 2.0 > row_end;
+        let needed_after = aspect_ratios.len() - index <= rows_after;
+
+        if index > start && rows_after > 0 && (past_end || needed_after) {
+            rows.push(start .. index);
+            start = index;
+        }
+        sum += aspect;
+    }
+
+    rows.push(start .. aspect_ratios.len());
+    rows
+}
+
+/// Stacks the views in columns (vertical) or rows (horizontal) of the same size, each view into the one which is
+/// the shortest so far. Cells keep about the aspect ratio of their image, but the views are no longer in order.
+pub struct MasonryLayout {}
+
+impl MasonryLayout {
+    pub fn new() -> Box<dyn Layout> {
+        Box::new(MasonryLayout {})
+    }
+}
+
+impl Layout for MasonryLayout {
+    fn layout(&self, aspect_ratios: &[f32], _selected: usize, width: i32, height: i32, direction: LayoutDirection) -> Vec<Rect> {
+        match direction {
+            LayoutDirection::Vertical => masonry(aspect_ratios, width, height),
+            LayoutDirection::Horizontal => {
+                let inverted: Vec<f32> = aspect_ratios.iter().map(|aspect| 1.0 / aspect).collect();
+                masonry(&inverted, height, width).into_iter().map(Rect::transposed).collect()
+            },
+        }
+    }
+}
+
+fn masonry(aspect_ratios: &[f32], width: i32, height: i32) -> Vec<Rect> {
+    // n columns of the same height are width * sum(1 / aspect) / n² high
+    let inverse_total: f32 = aspect_ratios.iter().map(|aspect| 1.0 / aspect).sum();
+    let expected = (width as f32 * inverse_total / height as f32).sqrt();
+    let candidates = candidate_counts(expected, aspect_ratios.len()).map(|column_count| {
+        let columns = stack(aspect_ratios, column_count);
+        let column_width = width as f32 / column_count as f32;
+        let worst = columns.iter()
+            .map(|column| {
+                let natural: f32 = column.iter().map(|&index| column_width / aspect_ratios[index]).sum();
+                distortion(natural, height)
+            })
+            .fold(0.0, f32::max);
+        (columns, worst)
+    });
+    let columns = match least_distorted(candidates) {
+        Some(columns) => columns,
+        None => return Vec::new(),
+    };
+
+    let mut cells = vec![Rect::default(); aspect_ratios.len()];
+    for (column, (x, column_width)) in columns.iter().zip(split(width, &vec![1.0; columns.len()])) {
+        let heights: Vec<f32> = column.iter().map(|&index| 1.0 / aspect_ratios[index]).collect();
+        for (&index, (y, cell_height)) in column.iter().zip(split(height, &heights)) {
+            cells[index] = Rect { x, y, width: column_width, height: cell_height };
+        }
+    }
+    cells
+}
+
+/// Returns the indices of the views in each of the `column_count` columns.
+fn stack(aspect_ratios: &[f32], column_count: usize) -> Vec<Vec<usize>> {
+    let mut columns = vec![Vec::new(); column_count];
+    let mut heights = vec![0.0f32; column_count];
+
+    for (index, aspect) in aspect_ratios.iter().enumerate() {
+        let shortest = (0 .. column_count)
+            .min_by(|&a, &b| heights[a].partial_cmp(&heights[b]).unwrap_or(Ordering::Equal))
+            .unwrap_or(0);
+        columns[shortest].push(index);
+        heights[shortest] += 1.0 / aspect;
+    }
+    columns
+}
+
+/// Part of the window taken by the thumbnail strip of `FocusLayout`.
+const FOCUS_STRIP: f32 = 0.2;
+
+/// Shows the selected view large and the others in a strip of thumbnails, on the right (horizontal)
+/// or at the bottom (vertical).
+pub struct FocusLayout {}
+
+impl FocusLayout {
+    pub fn new() -> Box<dyn Layout> {
+        Box::new(FocusLayout {})
+    }
+}
+
+impl Layout for FocusLayout {
+    fn layout(&self, aspect_ratios: &[f32], selected: usize, width: i32, height: i32, direction: LayoutDirection) -> Vec<Rect> {
+        match direction {
+            LayoutDirection::Vertical => focus(aspect_ratios.len(), selected, width, height),
+            LayoutDirection::Horizontal => {
+                focus(aspect_ratios.len(), selected, height, width).into_iter().map(Rect::transposed).collect()
+            },
+        }
+    }
+
+    /// The selected view moves into the large cell, so the neighbors are the view before (left and up)
+    /// and after it (right and down) instead of the ones next to its cell.
+    fn get_next_index(&self, cells: &[Rect], index: usize, x_off: i32, y_off: i32, edges: EdgeBehavior) -> usize {
+        let count = cells.len() as i32;
+        let next = index as i32 + (x_off + y_off).signum();
+        match edges {
+            _ if count == 0 => index,
+            EdgeBehavior::Wrap => ((next % count + count) % count) as usize,
+            EdgeBehavior::Stop => next.clamp(0, count - 1) as usize,
+        }
+    }
+}
+
+fn focus(view_count: usize, selected: usize, width: i32, height: i32) -> Vec<Rect> {
+    match view_count {
+        0 => return Vec::new(),
+        1 => return vec![Rect { x: 0, y: 0, width, height }],
+        _ => {},
+    }
+
+    let selected = selected.min(view_count - 1);
+    let strip = (height as f32 * FOCUS_STRIP).round() as i32;
+    let mut thumbnails = split(width, &vec![1.0; view_count - 1]).into_iter();
+
+    (0 .. view_count)
+        .map(|index| if index == selected {
+            Rect { x: 0, y: 0, width, height: height - strip }
+        } else {
+            let (x, thumbnail_width) = thumbnails.next().unwrap_or((0, 0));
+            Rect { x, y: height - strip, width: thumbnail_width, height: strip }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL: [LayoutKind; 5] = [LayoutKind::Grid, LayoutKind::Rows, LayoutKind::Masonry, LayoutKind::Focus, LayoutKind::Split];
+
+    fn rect(x: i32, y: i32, width: i32, height: i32) -> Rect {
+        Rect { x, y, width, height }
+    }
+
+    fn overlap(a: &Rect, b: &Rect) -> bool {
+        a.x < b.x + b.width && b.x < a.x + a.width && a.y < b.y + b.height && b.y < a.y + a.height
+    }
+
+    /// Aspect ratios between 1:3 and 3:1 which differ from view to view.
+    fn mixed_aspect_ratios(count: usize) -> Vec<f32> {
+        (0 .. count).map(|index| [1.0, 1.5, 0.5, 3.0, 0.75, 0.33][index % 6]).collect()
+    }
+
+    /// Every cell is inside the window and not empty, and no two cells overlap.
+    fn assert_cells_valid(cells: &[Rect], width: i32, height: i32) {
+        for (index, cell) in cells.iter().enumerate() {
+            assert!(cell.width > 0 && cell.height > 0, "cell {} of {:?} is empty", index, cells);
+            assert!(cell.x >= 0 && cell.y >= 0 && cell.x + cell.width <= width && cell.y + cell.height <= height,
+                "cell {} of {:?} is outside of {}x{}", index, cells, width, height);
+            for (other_index, other) in cells.iter().enumerate().skip(index + 1) {
+                assert!(!overlap(cell, other), "cells {} and {} of {:?} overlap", index, other_index, cells);
+            }
+        }
+    }
+
+    fn area(cells: &[Rect]) -> i32 {
+        cells.iter().map(|cell| cell.width * cell.height).sum()
+    }
+
+    #[test]
+    fn no_views() {
+        for kind in ALL.iter() {
+            for direction in [LayoutDirection::Horizontal, LayoutDirection::Vertical].iter() {
+                assert!(kind.create().layout(&[], 0, 640, 480, *direction).is_empty());
+            }
+        }
+    }
+
+    #[test]
+    fn single_view_fills_window() {
+        for kind in ALL.iter() {
+            for direction in [LayoutDirection::Horizontal, LayoutDirection::Vertical].iter() {
+                let cells = kind.create().layout(&[0.5], 0, 640, 480, *direction);
+                assert_eq!(cells, vec![rect(0, 0, 640, 480)], "{:?} {:?}", kind, direction);
+            }
+        }
+    }
+
+    #[test]
+    fn cells_are_valid() {
+        for kind in ALL.iter() {
+            for direction in [LayoutDirection::Horizontal, LayoutDirection::Vertical].iter() {
+                for count in 1 .. 40 {
+                    let aspect_ratios = mixed_aspect_ratios(count);
+                    let cells = kind.create().layout(&aspect_ratios, count / 2, 1280, 720, *direction);
+                    assert_eq!(cells.len(), count);
+                    assert_cells_valid(&cells, 1280, 720);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn packed_layouts_fill_window() {
+        for kind in [LayoutKind::Rows, LayoutKind::Masonry, LayoutKind::Focus, LayoutKind::Split].iter() {
+            for direction in [LayoutDirection::Horizontal, LayoutDirection::Vertical].iter() {
+                for count in 1 .. 20 {
+                    let cells = kind.create().layout(&mixed_aspect_ratios(count), 0, 1000, 700, *direction);
+                    assert_eq!(area(&cells), 1000 * 700, "{:?} {:?} with {} views", kind, direction, count);
                 }
-                let view = &mut views[index];
-                view.x = x * cell_width;
-                view.y = y * cell_height;
-                view.width = cell_width;
-                view.height = cell_height;
             }
         }
     }
 
-    fn get_next_index(&self, view_count: usize, index: usize, x_off: i32, y_off: i32, direction: LayoutDirection) -> usize {
-        let (grid_columns, grid_rows) = self.get_grid_size(view_count as i32, direction);
-        let x = ((index as i32 % grid_columns + x_off) + grid_columns) % grid_columns;
-        let y = ((index as i32 / grid_columns + y_off) + grid_rows) % grid_rows;
+    #[test]
+    fn grid_divides_evenly() {
+        let cells = GridLayout::new().layout(&[1.0, 1.0], 0, 640, 480, LayoutDirection::Horizontal);
+        assert_eq!(cells, vec![rect(0, 0, 320, 480), rect(320, 0, 320, 480)]);
 
-        let new_index = (x + y * grid_columns) as usize;
-        if new_index >= view_count - 1 {
-            return view_count - 1;
+        let cells = GridLayout::new().layout(&[1.0, 1.0], 0, 640, 480, LayoutDirection::Vertical);
+        assert_eq!(cells, vec![rect(0, 0, 640, 240), rect(0, 240, 640, 240)]);
+
+        let cells = GridLayout::new().layout(&[1.0; 3], 0, 600, 400, LayoutDirection::Horizontal);
+        assert_eq!(cells, vec![rect(0, 0, 300, 200), rect(300, 0, 300, 200), rect(0, 200, 300, 200)]);
+    }
+
+    #[test]
+    fn rows_match_aspect_ratios() {
+        // three 2:1 images fit exactly into a 400x600 window, one per row
+        let cells = RowLayout::new().layout(&[2.0; 3], 0, 400, 600, LayoutDirection::Horizontal);
+        assert_eq!(cells, vec![rect(0, 0, 400, 200), rect(0, 200, 400, 200), rect(0, 400, 400, 200)]);
+
+        // a wide and a square image share one row, sized by their aspect ratios
+        let cells = RowLayout::new().layout(&[2.0, 1.0], 0, 600, 200, LayoutDirection::Horizontal);
+        assert_eq!(cells, vec![rect(0, 0, 400, 200), rect(400, 0, 200, 200)]);
+
+        // vertically they are stacked into a column
+        let cells = RowLayout::new().layout(&[2.0, 1.0], 0, 200, 300, LayoutDirection::Vertical);
+        assert_eq!(cells, vec![rect(0, 0, 200, 100), rect(0, 100, 200, 200)]);
+    }
+
+    #[test]
+    fn rows_keep_order() {
+        let cells = RowLayout::new().layout(&mixed_aspect_ratios(12), 0, 1280, 720, LayoutDirection::Horizontal);
+        for pair in cells.windows(2) {
+            let same_row = pair[0].y == pair[1].y;
+            assert!(if same_row { pair[0].x < pair[1].x } else { pair[0].y < pair[1].y }, "{:?}", cells);
+        }
+    }
+
+    #[test]
+    fn partition_never_leaves_rows_empty() {
+        let aspect_ratios = [10.0, 0.1, 0.1, 0.1];
+        for row_count in 1 ..= aspect_ratios.len() {
+            let rows = partition(&aspect_ratios, row_count);
+            assert_eq!(rows.len(), row_count);
+            assert!(rows.iter().all(|row| !row.is_empty()), "{:?}", rows);
+            assert_eq!(rows.last().map(|row| row.end), Some(aspect_ratios.len()));
+        }
+    }
+
+    #[test]
+    fn candidates_contain_best_count() {
+        let row_distortion = |aspect_ratios: &[f32], row_count, width, height| {
+            let natural: f32 = partition(aspect_ratios, row_count).into_iter()
+                .map(|row| width as f32 / aspect_ratios[row].iter().sum::<f32>())
+                .sum();
+            distortion(natural, height)
+        };
+        let column_distortion = |aspect_ratios: &[f32], column_count, width, height| {
+            let column_width = width as f32 / column_count as f32;
+            stack(aspect_ratios, column_count).into_iter()
+                .map(|column| distortion(column.iter().map(|&index| column_width / aspect_ratios[index]).sum(), height))
+                .fold(0.0, f32::max)
+        };
+
+        for &(width, height) in &[(1280, 720), (400, 1600), (1600, 200)] {
+            for count in 1 .. 80 {
+                let aspect_ratios = mixed_aspect_ratios(count);
+                let best_rows = least_distorted((1 ..= count).map(|rows| (rows, row_distortion(&aspect_ratios, rows, width, height))));
+                let total: f32 = aspect_ratios.iter().sum();
+                let candidates = candidate_counts((total * height as f32 / width as f32).sqrt(), count);
+                assert!(candidates.contains(&best_rows.unwrap()), "{} rows not in {:?}", best_rows.unwrap(), candidates);
+
+                let best_columns = least_distorted((1 ..= count).map(|columns| (columns, column_distortion(&aspect_ratios, columns, width, height))));
+                let inverse_total: f32 = aspect_ratios.iter().map(|aspect| 1.0 / aspect).sum();
+                let candidates = candidate_counts((width as f32 * inverse_total / height as f32).sqrt(), count);
+                assert!(candidates.contains(&best_columns.unwrap()), "{} columns not in {:?}", best_columns.unwrap(), candidates);
+            }
+        }
+    }
+
+    #[test]
+    fn masonry_fills_shortest_column() {
+        let cells = MasonryLayout::new().layout(&[1.0; 4], 0, 400, 400, LayoutDirection::Vertical);
+        assert_eq!(cells, vec![rect(0, 0, 200, 200), rect(200, 0, 200, 200), rect(0, 200, 200, 200), rect(200, 200, 200, 200)]);
+
+        // the tall image fills the first column, so both square ones go into the second
+        let cells = MasonryLayout::new().layout(&[0.5, 1.0, 1.0], 0, 400, 400, LayoutDirection::Vertical);
+        assert_eq!(cells, vec![rect(0, 0, 200, 400), rect(200, 0, 200, 200), rect(200, 200, 200, 200)]);
+
+        // horizontally the wide image fills the first row
+        let cells = MasonryLayout::new().layout(&[2.0, 1.0, 1.0], 0, 400, 400, LayoutDirection::Horizontal);
+        assert_eq!(cells, vec![rect(0, 0, 400, 200), rect(0, 200, 200, 200), rect(200, 200, 200, 200)]);
+    }
+
+    #[test]
+    fn focus_shows_selected_large() {
+        let cells = FocusLayout::new().layout(&[1.0; 4], 2, 600, 500, LayoutDirection::Vertical);
+        assert_eq!(cells, vec![rect(0, 400, 200, 100), rect(200, 400, 200, 100), rect(0, 0, 600, 400), rect(400, 400, 200, 100)]);
+
+        let cells = FocusLayout::new().layout(&[1.0; 3], 0, 500, 600, LayoutDirection::Horizontal);
+        assert_eq!(cells, vec![rect(0, 0, 400, 600), rect(400, 0, 100, 300), rect(400, 300, 100, 300)]);
+
+        // a selection past the end focuses the last view
+        let cells = FocusLayout::new().layout(&[1.0; 2], 5, 100, 100, LayoutDirection::Vertical);
+        assert_eq!(cells[1], rect(0, 0, 100, 80));
+    }
+
+    fn grid(count: usize, direction: LayoutDirection) -> Vec<Rect> {
+        GridLayout::new().layout(&vec![1.0; count], 0, 900, 900, direction)
+    }
+
+    fn center(cell: &Rect) -> (i32, i32) {
+        (2 * cell.x + cell.width, 2 * cell.y + cell.height)
+    }
+
+    const STEPS: [(i32, i32); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+
+    #[test]
+    fn grid_navigation_with_short_last_row() {
+        // 0 1 2
+        // 3 4 5
+        // 6
+        let cells = grid(7, LayoutDirection::Horizontal);
+        let layout = GridLayout::new();
+        let next = |index, x_off, y_off, edges| layout.get_next_index(&cells, index, x_off, y_off, edges);
+
+        assert_eq!(next(2, 0, 1, EdgeBehavior::Wrap), 5);
+        assert_eq!(next(5, 0, 1, EdgeBehavior::Wrap), 2);
+        assert_eq!(next(5, 0, 1, EdgeBehavior::Stop), 5);
+        assert_eq!(next(4, 0, 1, EdgeBehavior::Wrap), 1);
+        assert_eq!(next(3, 0, 1, EdgeBehavior::Wrap), 6);
+        assert_eq!(next(6, 0, 1, EdgeBehavior::Wrap), 0);
+        assert_eq!(next(6, 0, -1, EdgeBehavior::Stop), 3);
+        assert_eq!(next(6, 1, 0, EdgeBehavior::Wrap), 6);
+        assert_eq!(next(6, -1, 0, EdgeBehavior::Stop), 6);
+        assert_eq!(next(5, 1, 0, EdgeBehavior::Wrap), 3);
+        assert_eq!(next(5, 1, 0, EdgeBehavior::Stop), 5);
+        assert_eq!(next(3, -1, 0, EdgeBehavior::Wrap), 5);
+        assert_eq!(next(0, -1, -1, EdgeBehavior::Wrap), 5);
+    }
+
+    #[test]
+    fn grid_navigation_wraps_within_rows_and_columns() {
+        let layout = GridLayout::new();
+        for direction in [LayoutDirection::Horizontal, LayoutDirection::Vertical].iter() {
+            for count in 1 .. 40 {
+                let cells = grid(count, *direction);
+                for index in 0 .. count {
+                    for (x_off, y_off) in STEPS.iter() {
+                        // going around a row or column ends where it started
+                        let mut current = index;
+                        for _ in 0 .. count {
+                            current = layout.get_next_index(&cells, current, *x_off, *y_off, EdgeBehavior::Wrap);
+                            if current == index {
+                                break;
+                            }
+                            assert_eq!(center(&cells[current]).0 == center(&cells[index]).0, *x_off == 0, "{} views", count);
+                        }
+                        assert_eq!(current, index, "{} views, from {} by ({}, {})", count, index, x_off, y_off);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn grid_navigation_reaches_every_view() {
+        let layout = GridLayout::new();
+        for count in 1 .. 40 {
+            let cells = grid(count, LayoutDirection::Horizontal);
+            let mut reached = vec![false; count];
+            let mut queue = vec![0];
+            while let Some(index) = queue.pop() {
+                if !reached[index] {
+                    reached[index] = true;
+                    for (x_off, y_off) in STEPS.iter() {
+                        queue.push(layout.get_next_index(&cells, index, *x_off, *y_off, EdgeBehavior::Stop));
+                    }
+                }
+            }
+            assert!(reached.iter().all(|reached| *reached), "{} views", count);
+        }
+    }
+
+    #[test]
+    fn navigation_moves_in_the_requested_direction() {
+        for kind in [LayoutKind::Grid, LayoutKind::Rows, LayoutKind::Masonry, LayoutKind::Split].iter() {
+            let layout = kind.create();
+            for direction in [LayoutDirection::Horizontal, LayoutDirection::Vertical].iter() {
+                for count in 1 .. 30 {
+                    let cells = layout.layout(&mixed_aspect_ratios(count), 0, 1280, 720, *direction);
+                    for index in 0 .. count {
+                        for (x_off, y_off) in STEPS.iter() {
+                            let next = layout.get_next_index(&cells, index, *x_off, *y_off, EdgeBehavior::Stop);
+                            if next == index {
+                                continue;
+                            }
+                            let (from, to) = (center(&cells[index]), center(&cells[next]));
+                            let moved = (to.0 - from.0) * x_off + (to.1 - from.1) * y_off;
+                            assert!(moved > 0, "{:?} {:?} with {} views, from {} by ({}, {}) to {}",
+                                kind, direction, count, index, x_off, y_off, next);
+                        }
+                    }
+                }
+            }
         }
-        return new_index;
+    }
+
+    #[test]
+    fn focus_navigation_steps_in_order() {
+        let layout = FocusLayout::new();
+        let cells = layout.layout(&[1.0; 4], 1, 800, 600, LayoutDirection::Vertical);
+        assert_eq!(layout.get_next_index(&cells, 1, 1, 0, EdgeBehavior::Wrap), 2);
+        assert_eq!(layout.get_next_index(&cells, 1, 0, -1, EdgeBehavior::Wrap), 0);
+        assert_eq!(layout.get_next_index(&cells, 3, 0, 1, EdgeBehavior::Wrap), 0);
+        assert_eq!(layout.get_next_index(&cells, 3, 0, 1, EdgeBehavior::Stop), 3);
+        assert_eq!(layout.get_next_index(&cells, 0, -1, 0, EdgeBehavior::Stop), 0);
+    }
+
+    #[test]
+    fn split_adds_up() {
+        assert_eq!(split(10, &[1.0, 1.0, 1.0]), vec![(0, 3), (3, 4), (7, 3)]);
+        assert_eq!(split(100, &[3.0, 1.0]), vec![(0, 75), (75, 25)]);
     }
 }
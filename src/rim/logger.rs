@@ -0,0 +1,201 @@
+use std::collections::VecDeque;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::*;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use imgui::*;
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+/// Environment variable with the log level, used if `--log-level` isn't given.
+pub const LOG_ENV: &str = "RIM_LOG";
+
+/// Number of records kept for the log viewer.
+const MAX_RECORDS: usize = 2000;
+
+pub struct LogRecord {
+    pub level   : Level,
+    pub target  : String,
+    pub message : String,
+    /// Seconds since the unix epoch.
+    pub time    : f64,
+}
+
+/// The most recent log records, shared between the logger and the log viewer.
+pub struct LogBuffer {
+    records : Mutex<VecDeque<LogRecord>>,
+}
+
+struct Logger {
+    level   : LevelFilter,
+    file    : Option<Mutex<File>>,
+    buffer  : Arc<LogBuffer>,
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let time = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0.0, |time| time.as_secs_f64());
+        let line = format!("{:.3} [{:5}] {}: {}", time, record.level(), record.target(), record.args());
+
+        eprintln!("{}", line);
+        if let Some(file) = &self.file {
+            if let Ok(mut file) = file.lock() {
+                writeln!(file, "{}", line).unwrap_or(());
+            }
+        }
+
+        if let Ok(mut records) = self.buffer.records.lock() {
+            if records.len() >= MAX_RECORDS {
+                records.pop_front();
+            }
+            records.push_back(LogRecord {
+                level   : record.level(),
+                target  : record.target().to_owned(),
+                message : record.args().to_string(),
+                time,
+            });
+        }
+    }
+
+    fn flush(&self) {
+        if let Some(file) = &self.file {
+            if let Ok(mut file) = file.lock() {
+                file.flush().unwrap_or(());
+            }
+        }
+    }
+}
+
+/// Parses `off`, `error`, `warn`, `info`, `debug` or `trace`.
+pub fn parse_level(name: &str) -> Option<LevelFilter> {
+    name.trim().parse().ok()
+}
+
+/// Installs the global logger. `level` defaults to `$RIM_LOG`, and to `warn` if that isn't set either.
+/// With `file` all records are also appended to that file.
+pub fn init(level: Option<LevelFilter>, file: Option<&Path>) -> Result<Arc<LogBuffer>, String> {
+    let level = match level {
+        Some(level) => level,
+        None => match std::env::var(LOG_ENV) {
+            Ok(name) => match parse_level(&name) {
+                Some(level) => level,
+                None => return Err(format!("Invalid log level '{}' in {}", name, LOG_ENV)),
+            },
+            Err(_) => LevelFilter::Warn,
+        },
+    };
+
+    let file = match file {
+        Some(path) => match OpenOptions::new().create(true).append(true).open(path) {
+            Ok(file) => Some(Mutex::new(file)),
+            Err(err) => return Err(format!("Failed to open log file {:?}: {}", path, err)),
+        },
+        None => None,
+    };
+
+    let buffer = Arc::new(LogBuffer {
+        records : Mutex::new(VecDeque::new()),
+    });
+
+    let logger = Logger {
+        level,
+        file,
+        buffer  : buffer.clone(),
+    };
+    if let Err(err) = log::set_boxed_logger(Box::new(logger)) {
+        return Err(err.to_string());
+    }
+    log::set_max_level(level);
+
+    Ok(buffer)
+}
+
+/// Window showing the records of a `LogBuffer`, filtered by level.
+pub struct LogViewer {
+    buffer      : Arc<LogBuffer>,
+    level       : LevelFilter,
+    auto_scroll : bool,
+    open        : bool,
+}
+
+impl LogViewer {
+    pub fn new(buffer: Arc<LogBuffer>) -> LogViewer {
+        LogViewer {
+            buffer,
+            level       : LevelFilter::Trace,
+            auto_scroll : true,
+            open        : false,
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+    }
+
+    pub fn render(&mut self, ui: &Ui, window_size: (u32, u32)) {
+        if !self.open {
+            return;
+        }
+
+        let mut open = self.open;
+        let mut clear = false;
+        let buffer = self.buffer.clone();
+        let level = &mut self.level;
+        let auto_scroll = &mut self.auto_scroll;
+
+        Window::new(im_str!("Log"))
+            .opened(&mut open)
+            .size([std::cmp::min(800, window_size.0) as f32, std::cmp::min(400, window_size.1) as f32], Condition::FirstUseEver)
+            .position([40.0, 40.0], Condition::FirstUseEver)
+            .collapsible(false)
+            .build(ui, || {
+                const LEVELS: [LevelFilter; 5] = [LevelFilter::Error, LevelFilter::Warn, LevelFilter::Info, LevelFilter::Debug, LevelFilter::Trace];
+                for filter in LEVELS.iter() {
+                    ui.radio_button(&im_str!("{}", filter), level, *filter);
+                    ui.same_line(0.0);
+                }
+                ui.checkbox(im_str!("Auto scroll"), auto_scroll);
+                ui.same_line(0.0);
+                if ui.small_button(im_str!("Clear")) {
+                    clear = true;
+                }
+                ui.separator();
+
+                ChildWindow::new(im_str!("records")).build(ui, || {
+                    if let Ok(records) = buffer.records.lock() {
+                        for record in records.iter().filter(|record| record.level <= *level) {
+                            let color = match record.level {
+                                Level::Error => [1.0, 0.4, 0.4, 1.0],
+                                Level::Warn => [1.0, 0.8, 0.3, 1.0],
+                                Level::Info => [0.9, 0.9, 0.9, 1.0],
+                                Level::Debug | Level::Trace => [0.6, 0.6, 0.6, 1.0],
+                            };
+                            ui.text_colored(color, &im_str!("[{:5}] {}: {}", record.level, record.target, record.message));
+                        }
+                    }
+
+                    if *auto_scroll && ui.scroll_y() >= ui.scroll_max_y() {
+                        unsafe {
+                            imgui::sys::igSetScrollHereY(1.0);
+                        }
+                    }
+                });
+            });
+
+        self.open = open;
+        if clear {
+            if let Ok(mut records) = self.buffer.records.lock() {
+                records.clear();
+            }
+        }
+    }
+}
@@ -6,4 +6,27 @@ pub mod layout;
 pub mod app;
 pub mod vec;
 pub mod open_file_dialog;
-pub mod util;
\ No newline at end of file
+pub mod command_palette;
+pub mod util;
+pub mod action;
+pub mod keymap;
+pub mod config;
+pub mod session;
+pub mod ipc;
+pub mod stream;
+pub mod remote;
+pub mod paths;
+pub mod notifications;
+pub mod error;
+pub mod logger;
+pub mod debug_overlay;
+pub mod texture_budget;
+pub mod tiles;
+pub mod image_cache;
+pub mod renderer;
+pub mod gl_renderer;
+pub mod software_renderer;
+pub mod headless;
+pub mod split_layout;
+pub mod group;
+pub mod wake;
\ No newline at end of file
@@ -0,0 +1,168 @@
+use imgui::*;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+impl Severity {
+    fn color(self) -> [f32; 4] {
+        match self {
+            Severity::Info      => [0.8, 0.8, 0.8, 1.0],
+            Severity::Warning   => [1.0, 0.8, 0.3, 1.0],
+            Severity::Error     => [1.0, 0.4, 0.4, 1.0],
+        }
+    }
+
+    /// How long a toast stays visible, errors are shown longer so they can be read.
+    fn lifetime(self) -> Duration {
+        match self {
+            Severity::Info      => Duration::from_secs(3),
+            Severity::Warning   => Duration::from_secs(6),
+            Severity::Error     => Duration::from_secs(10),
+        }
+    }
+}
+
+struct Notification {
+    severity    : Severity,
+    message     : String,
+    created     : Instant,
+}
+
+/// Messages shown as toasts in the bottom right corner which disappear after a while.
+/// Unlike a modal popup they don't block input to the views. All messages are kept in a log
+/// which can be shown on demand.
+pub struct Notifications {
+    toasts      : Vec<Notification>,
+    history     : Vec<Notification>,
+    show_history: bool,
+}
+
+/// Older messages are dropped from the log.
+const MAX_HISTORY: usize = 500;
+
+impl Notifications {
+    pub fn new() -> Notifications {
+        Notifications {
+            toasts      : Vec::new(),
+            history     : Vec::new(),
+            show_history: false,
+        }
+    }
+
+    pub fn info(&mut self, msg: String) {
+        self.push(Severity::Info, msg);
+    }
+
+    pub fn warning(&mut self, msg: String) {
+        self.push(Severity::Warning, msg);
+    }
+
+    pub fn error(&mut self, msg: String) {
+        self.push(Severity::Error, msg);
+    }
+
+    /// Adds a message. If the same message is already shown its toast is restarted instead.
+    pub fn push(&mut self, severity: Severity, msg: String) {
+        match severity {
+            Severity::Info      => log::info!("{}", msg),
+            Severity::Warning   => log::warn!("{}", msg),
+            Severity::Error     => log::error!("{}", msg),
+        }
+
+        let now = Instant::now();
+        self.toasts.retain(|toast| toast.message != msg);
+        self.toasts.push(Notification { severity, message: msg.clone(), created: now });
+
+        self.history.push(Notification { severity, message: msg, created: now });
+        if self.history.len() > MAX_HISTORY {
+            self.history.remove(0);
+        }
+    }
+
+    /// Hides all toasts, they stay in the log.
+    pub fn clear(&mut self) {
+        self.toasts.clear();
+    }
+
+    pub fn has_toasts(&self) -> bool {
+        !self.toasts.is_empty()
+    }
+
+    pub fn toggle_history(&mut self) {
+        self.show_history = !self.show_history;
+    }
+
+    pub fn render(&mut self, ui: &Ui, window_size: (u32, u32)) {
+        let now = Instant::now();
+        self.toasts.retain(|toast| now.duration_since(toast.created) < toast.severity.lifetime());
+
+        if !self.toasts.is_empty() {
+            self.render_toasts(ui, window_size);
+        }
+
+        if self.show_history {
+            self.render_history(ui, window_size);
+        }
+    }
+
+    fn render_toasts(&mut self, ui: &Ui, window_size: (u32, u32)) {
+        let mut dismissed = None;
+
+        Window::new(im_str!("##Notifications"))
+            .position([window_size.0 as f32 - 10.0, window_size.1 as f32 - 10.0], Condition::Always)
+            .position_pivot([1.0, 1.0])
+            .size_constraints([200.0, 0.0], [window_size.0 as f32 * 0.5, window_size.1 as f32 * 0.5])
+            .always_auto_resize(true)
+            .focus_on_appearing(false)
+            .title_bar(false)
+            .resizable(false)
+            .build(ui, || {
+                for (i, toast) in self.toasts.iter().enumerate() {
+                    if ui.small_button(&im_str!("x##{}", i)) {
+                        dismissed = Some(i);
+                    }
+                    ui.same_line(0.0);
+                    ui.text_colored(toast.severity.color(), &im_str!("{}", toast.message));
+                }
+            });
+
+        if let Some(index) = dismissed {
+            self.toasts.remove(index);
+        }
+    }
+
+    fn render_history(&mut self, ui: &Ui, window_size: (u32, u32)) {
+        let mut open = self.show_history;
+        let mut clear = false;
+        let now = Instant::now();
+
+        Window::new(im_str!("Notification Log"))
+            .opened(&mut open)
+            .size([std::cmp::min(600, window_size.0) as f32, std::cmp::min(300, window_size.1) as f32], Condition::FirstUseEver)
+            .position([20.0, 20.0], Condition::FirstUseEver)
+            .collapsible(false)
+            .build(ui, || {
+                if ui.small_button(im_str!("Clear")) {
+                    clear = true;
+                }
+                ui.separator();
+
+                for entry in self.history.iter().rev() {
+                    let age = now.duration_since(entry.created).as_secs();
+                    ui.text_disabled(&im_str!("{:>4}s ago", age));
+                    ui.same_line(0.0);
+                    ui.text_colored(entry.severity.color(), &im_str!("{}", entry.message));
+                }
+            });
+
+        self.show_history = open;
+        if clear {
+            self.history.clear();
+        }
+    }
+}
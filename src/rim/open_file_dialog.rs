@@ -1,6 +1,7 @@
 use imgui::*;
 use std::path::*;
 use std::fs;
+use super::error::RimError;
 use super::util::get_absolute_path;
 
 pub struct OpenFileDialog {
@@ -8,6 +9,8 @@ pub struct OpenFileDialog {
     item_list   : Vec<String>,
     selected    : usize,
     open        : bool,
+    /// Why the current directory couldn't be listed.
+    error       : Option<RimError>,
 }
 
 impl OpenFileDialog {
@@ -17,6 +20,7 @@ impl OpenFileDialog {
             item_list   : Vec::new(),
             selected    : 0,
             open        : false,
+            error       : None,
         }
     }
 
@@ -39,6 +43,7 @@ impl OpenFileDialog {
             .save_settings(true)
             .always_auto_resize(true)
             .build(|| {
+                let dir = self.current_dir.clone();
                 let mut selected = self.selected;
                 if ui.is_window_focused() && !ui.is_key_down(sdl2::keyboard::Scancode::Application as u32) {
                     if ui.is_key_pressed(sdl2::keyboard::Scancode::I as u32) || ui.is_key_pressed(sdl2::keyboard::Scancode::Up as u32) {
@@ -87,6 +92,10 @@ impl OpenFileDialog {
                     }
                 }
 
+                if let Some(err) = &self.error {
+                    ui.text_colored([1.0, 0.4, 0.4, 1.0], &im_str!("{}", err));
+                }
+
                 let mut clicked_on_item = None;
                 for (i, path) in self.item_list.iter().enumerate() {
                     let p = im_str!("{}", path);
@@ -129,7 +138,10 @@ impl OpenFileDialog {
                     }
                 }
 
-                self.selected = selected;
+                // moving to another directory selected its first item
+                if self.current_dir == dir {
+                    self.selected = selected;
+                }
             });
 
         self.open &= open;
@@ -149,13 +161,26 @@ impl OpenFileDialog {
         }
     }
 
-    fn update_list(&mut self){ 
+    /// Lists the current directory, if it can't be read only `..` is shown so the user can go back.
+    fn update_list(&mut self) {
         self.item_list.clear();
         self.item_list.push("..".to_owned());
-        for item in fs::read_dir(get_absolute_path(&PathBuf::from(&self.current_dir))).unwrap() {
+        self.error = None;
+
+        let dir = PathBuf::from(&self.current_dir);
+        let entries = match get_absolute_path(&dir).and_then(|dir| fs::read_dir(&dir).map_err(|err| RimError::io(&dir, err))) {
+            Ok(entries) => entries,
+            Err(err) => {
+                self.error = Some(err);
+                self.selected = 0;
+                return;
+            },
+        };
+
+        for item in entries {
             match item {
                 Ok(item) => {
-                    let mut name = item.file_name().to_str().unwrap().to_owned();
+                    let mut name = item.file_name().to_string_lossy().into_owned();
                     if item.path().is_dir() {
                         name += "/";
                     }
@@ -168,13 +193,14 @@ impl OpenFileDialog {
         }
 
         self.item_list[1..].sort();
-        self.selected %= self.item_list.len();
+        self.selected = std::cmp::min(self.selected, self.item_list.len() - 1);
     }
 
     fn move_dir_up(&mut self) {
         match PathBuf::from(&self.current_dir).parent() {
             Some(parent) => {
-                self.current_dir = parent.to_str().unwrap().to_owned();
+                self.current_dir = parent.to_string_lossy().into_owned();
+                self.selected = 0;
                 self.update_list();
             }
 
@@ -185,10 +211,17 @@ impl OpenFileDialog {
     fn move_dir_down(&mut self, dir: &str) {
         let mut path = PathBuf::from(&self.current_dir);
         path.push(dir);
-        path = get_absolute_path(&path);
+        let path = match get_absolute_path(&path) {
+            Ok(path) => path,
+            Err(err) => {
+                self.error = Some(err);
+                return;
+            },
+        };
 
         if path.is_dir() {
-            self.current_dir = path.to_str().unwrap().to_owned();
+            self.current_dir = path.to_string_lossy().into_owned();
+            self.selected = 0;
             self.update_list();
         }
     }
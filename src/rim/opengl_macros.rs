@@ -1,4 +1,4 @@
-
+/// Runs a `gl` call and logs every error it raised, with the call and where it was made.
 #[macro_export]
 macro_rules! GL {
     ($expression:expr) => {
@@ -11,8 +11,84 @@ macro_rules! GL {
                     break;
                 }
 
-                println!("[OpenGL] Error: {}", gl_error);
+                log::error!("[OpenGL] {} ({:#06x}) in {} at {}:{}",
+                    $crate::rim::opengl_macros::error_name(gl_error), gl_error,
+                    stringify!($expression), file!(), line!());
             }
         }
     };
 }
+
+pub fn error_name(error: gl::types::GLenum) -> &'static str {
+    match error {
+        gl::INVALID_ENUM                    => "GL_INVALID_ENUM",
+        gl::INVALID_VALUE                   => "GL_INVALID_VALUE",
+        gl::INVALID_OPERATION               => "GL_INVALID_OPERATION",
+        gl::INVALID_FRAMEBUFFER_OPERATION   => "GL_INVALID_FRAMEBUFFER_OPERATION",
+        gl::OUT_OF_MEMORY                   => "GL_OUT_OF_MEMORY",
+        gl::STACK_UNDERFLOW                 => "GL_STACK_UNDERFLOW",
+        gl::STACK_OVERFLOW                  => "GL_STACK_OVERFLOW",
+        _                                   => "unknown error",
+    }
+}
+
+/// Forwards messages of the driver to the log, if it supports `KHR_debug`.
+/// Only useful with a debug context, see `App::new`.
+pub fn enable_debug_output() {
+    if !gl::DebugMessageCallback::is_loaded() {
+        log::debug!("[OpenGL] KHR_debug is not supported, driver messages won't be logged");
+        return;
+    }
+
+    GL!(Enable(DEBUG_OUTPUT));
+    // Report messages during the call which caused them, so they show up next to the right log lines
+    GL!(Enable(DEBUG_OUTPUT_SYNCHRONOUS));
+    GL!(DebugMessageCallback(Some(debug_callback), std::ptr::null()));
+}
+
+extern "system" fn debug_callback(
+    source      : gl::types::GLenum,
+    kind        : gl::types::GLenum,
+    id          : gl::types::GLuint,
+    severity    : gl::types::GLenum,
+    length      : gl::types::GLsizei,
+    message     : *const gl::types::GLchar,
+    _user_param : *mut std::ffi::c_void) {
+
+    let message = if message.is_null() {
+        String::new()
+    } else if length < 0 {
+        unsafe { std::ffi::CStr::from_ptr(message) }.to_string_lossy().into_owned()
+    } else {
+        let bytes = unsafe { std::slice::from_raw_parts(message as *const u8, length as usize) };
+        String::from_utf8_lossy(bytes).into_owned()
+    };
+
+    let level = match severity {
+        gl::DEBUG_SEVERITY_HIGH     => log::Level::Error,
+        gl::DEBUG_SEVERITY_MEDIUM   => log::Level::Warn,
+        gl::DEBUG_SEVERITY_LOW      => log::Level::Info,
+        _                           => log::Level::Debug,
+    };
+
+    let source = match source {
+        gl::DEBUG_SOURCE_API                => "api",
+        gl::DEBUG_SOURCE_WINDOW_SYSTEM      => "window system",
+        gl::DEBUG_SOURCE_SHADER_COMPILER    => "shader compiler",
+        gl::DEBUG_SOURCE_THIRD_PARTY        => "third party",
+        gl::DEBUG_SOURCE_APPLICATION        => "application",
+        _                                   => "other",
+    };
+
+    let kind = match kind {
+        gl::DEBUG_TYPE_ERROR                => "error",
+        gl::DEBUG_TYPE_DEPRECATED_BEHAVIOR  => "deprecated",
+        gl::DEBUG_TYPE_UNDEFINED_BEHAVIOR   => "undefined behavior",
+        gl::DEBUG_TYPE_PORTABILITY          => "portability",
+        gl::DEBUG_TYPE_PERFORMANCE          => "performance",
+        gl::DEBUG_TYPE_MARKER               => "marker",
+        _                                   => "other",
+    };
+
+    log::log!(level, "[OpenGL] {} {} {}: {}", source, kind, id, message);
+}
@@ -0,0 +1,296 @@
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::fs;
+use std::io::Read;
+use std::path::*;
+use std::time::SystemTime;
+
+use super::remote::is_url;
+use super::stream::{is_stdin, is_fifo};
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum SortOrder {
+    /// Lexicographic by path.
+    Name,
+    /// By path, but numbers are compared by value, so `frame2` comes before `frame10`.
+    Natural,
+    /// Oldest first.
+    Modified,
+    /// Smallest first.
+    Size,
+}
+
+impl SortOrder {
+    pub fn from_name(name: &str) -> Option<SortOrder> {
+        match name {
+            "name" => Some(SortOrder::Name),
+            "natural" => Some(SortOrder::Natural),
+            "mtime" => Some(SortOrder::Modified),
+            "size" => Some(SortOrder::Size),
+            _ => None,
+        }
+    }
+}
+
+/// How the paths given on the command line are turned into the list of files to open.
+#[derive(Debug, Clone, Default)]
+pub struct PathOptions {
+    pub recursive   : bool,
+    pub max_count   : Option<usize>,
+    /// `None` keeps the order of the arguments, files in a directory are sorted by name.
+    pub sort        : Option<SortOrder>,
+}
+
+pub struct ExpandedPaths {
+    pub paths   : Vec<PathBuf>,
+    /// Arguments which didn't match anything and directories which couldn't be read.
+    pub errors  : Vec<String>,
+    /// Number of files in directories or matched by patterns which are not images.
+    pub skipped : usize,
+}
+
+/// Expands directories and glob patterns into files. Stdin, URLs and named pipes are kept as they are.
+/// Files found in directories or by patterns are only kept if they look like images, files given
+/// explicitly are always kept so the reason they can't be opened can be reported.
+pub fn expand_paths(args: &[PathBuf], options: &PathOptions) -> ExpandedPaths {
+    let mut expanded = ExpandedPaths {
+        paths   : Vec::new(),
+        errors  : Vec::new(),
+        skipped : 0,
+    };
+
+    for arg in args.iter() {
+        if is_stdin(arg) || arg.to_str().is_some_and(is_url) || (arg.exists() && !arg.is_dir()) {
+            expanded.paths.push(arg.clone());
+        } else if arg.is_dir() {
+            add_dir(arg, options.recursive, &mut expanded);
+        } else if let Some(pattern) = arg.to_str().filter(|arg| is_glob(arg)) {
+            match glob::glob(pattern) {
+                Ok(entries) => {
+                    let count = expanded.paths.len();
+                    for entry in entries {
+                        match entry {
+                            Ok(path) => add_entry(&path, options.recursive, &mut expanded),
+                            Err(err) => expanded.errors.push(err.to_string()),
+                        }
+                    }
+                    if expanded.paths.len() == count {
+                        expanded.errors.push(format!("No images match {:?}", arg));
+                    }
+                },
+                Err(err) => expanded.errors.push(format!("Invalid pattern {:?}: {}", arg, err)),
+            }
+        } else {
+            expanded.errors.push(format!("path is not a file or directory: {:?}", arg));
+        }
+    }
+
+    // a file matched by several arguments is opened once, at its first position
+    let mut seen = HashSet::new();
+    expanded.paths.retain(|path| seen.insert(path.clone()));
+
+    if let Some(order) = options.sort {
+        sort_paths(&mut expanded.paths, order);
+    }
+
+    if let Some(max_count) = options.max_count {
+        expanded.paths.truncate(max_count);
+    }
+
+    expanded
+}
+
+fn is_glob(path: &str) -> bool {
+    path.contains(['*', '?', '['])
+}
+
+/// Adds the images in a directory sorted by name.
+fn add_dir(path: &Path, recursive: bool, expanded: &mut ExpandedPaths) {
+    let mut entries: Vec<PathBuf> = match fs::read_dir(path) {
+        Ok(dir) => dir.filter_map(|entry| entry.ok()).map(|entry| entry.path()).collect(),
+        Err(err) => {
+            expanded.errors.push(format!("Failed to load files in directory {:?}: {}", path, err));
+            return;
+        },
+    };
+    entries.sort();
+
+    for entry in entries.iter() {
+        add_entry(entry, recursive, expanded);
+    }
+}
+
+/// Adds a file found in a directory or by a pattern if it is an image.
+fn add_entry(path: &Path, recursive: bool, expanded: &mut ExpandedPaths) {
+    if path.is_dir() {
+        if recursive {
+            add_dir(path, recursive, expanded);
+        }
+    } else if is_image_file(path) || is_fifo(path) {
+        expanded.paths.push(path.to_owned());
+    } else {
+        expanded.skipped += 1;
+    }
+}
+
+/// Checks the extension, and the first bytes of the file if the extension is unknown.
+pub fn is_image_file(path: &Path) -> bool {
+    if image::ImageFormat::from_path(path).is_ok() {
+        return true;
+    }
+
+    let mut header = [0u8; 16];
+    let read = match fs::File::open(path).and_then(|mut file| file.read(&mut header)) {
+        Ok(read) => read,
+        Err(_) => return false,
+    };
+    image::guess_format(&header[..read]).is_ok()
+}
+
+pub fn sort_paths(paths: &mut [PathBuf], order: SortOrder) {
+    match order {
+        SortOrder::Name => paths.sort(),
+        SortOrder::Natural => paths.sort_by(|a, b| natural_cmp(&a.to_string_lossy(), &b.to_string_lossy())),
+        // paths without metadata, e.g. URLs, go first
+        SortOrder::Modified => paths.sort_by_cached_key(|path| {
+            fs::metadata(path).and_then(|metadata| metadata.modified()).unwrap_or(SystemTime::UNIX_EPOCH)
+        }),
+        SortOrder::Size => paths.sort_by_cached_key(|path| fs::metadata(path).map_or(0, |metadata| metadata.len())),
+    }
+}
+
+/// Compares strings like `Ord`, except that runs of digits are compared by their numeric value.
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut a = a.chars().peekable();
+    let mut b = b.chars().peekable();
+
+    loop {
+        match (a.peek().copied(), b.peek().copied()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(ca), Some(cb)) if ca.is_ascii_digit() && cb.is_ascii_digit() => {
+                let mut digits_a = String::new();
+                while let Some(c) = a.peek().copied().filter(char::is_ascii_digit) {
+                    digits_a.push(c);
+                    a.next();
+                }
+                let mut digits_b = String::new();
+                while let Some(c) = b.peek().copied().filter(char::is_ascii_digit) {
+                    digits_b.push(c);
+                    b.next();
+                }
+
+                // compare by value without parsing, so arbitrarily long numbers work
+                let trimmed_a = digits_a.trim_start_matches('0');
+                let trimmed_b = digits_b.trim_start_matches('0');
+                let ordering = trimmed_a.len().cmp(&trimmed_b.len())
+                    .then_with(|| trimmed_a.cmp(trimmed_b))
+                    .then_with(|| digits_a.len().cmp(&digits_b.len()));
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            },
+            (Some(ca), Some(cb)) => {
+                if ca != cb {
+                    return ca.cmp(&cb);
+                }
+                a.next();
+                b.next();
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An empty directory for one test, with the given files. Names ending in `.png` are images.
+    fn temp_dir(name: &str, files: &[&str]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("rim-paths-test-{}-{}", name, std::process::id()));
+        fs::remove_dir_all(&dir).unwrap_or(());
+        for file in files.iter() {
+            let path = dir.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, "not decoded").unwrap();
+        }
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn names(dir: &Path, paths: &[PathBuf]) -> Vec<String> {
+        paths.iter()
+            .map(|path| path.strip_prefix(dir).unwrap().to_string_lossy().into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn natural_cmp_compares_digit_runs_by_value() {
+        assert_eq!(natural_cmp("frame2", "frame10"), Ordering::Less);
+        assert_eq!(natural_cmp("frame10", "frame9"), Ordering::Greater);
+        assert_eq!(natural_cmp("a9b", "a10a"), Ordering::Less);
+        assert_eq!(natural_cmp("frame10", "frame10"), Ordering::Equal);
+        assert_eq!(natural_cmp("frame", "frame1"), Ordering::Less);
+        assert_eq!(natural_cmp("99999999999999999999999", "100000000000000000000000"), Ordering::Less);
+    }
+
+    #[test]
+    fn natural_cmp_leading_zeros() {
+        assert_eq!(natural_cmp("007", "8"), Ordering::Less);
+        assert_eq!(natural_cmp("010", "9"), Ordering::Greater);
+        // equal values are ordered by the number of digits, so the order is still total
+        assert_eq!(natural_cmp("1", "01"), Ordering::Less);
+        assert_eq!(natural_cmp("01a", "1b"), Ordering::Greater);
+    }
+
+    #[test]
+    fn glob_without_matches() {
+        let dir = temp_dir("glob", &["a.png"]);
+        let expanded = expand_paths(&[dir.join("*.jpg")], &PathOptions::default());
+        assert!(expanded.paths.is_empty());
+        assert_eq!(expanded.errors.len(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn directories_are_only_traversed_recursively_if_asked() {
+        let dir = temp_dir("recursive", &["b.png", "a/c.png", "a/b/d.png"]);
+
+        let expanded = expand_paths(std::slice::from_ref(&dir), &PathOptions::default());
+        assert_eq!(names(&dir, &expanded.paths), ["b.png"]);
+
+        let options = PathOptions { recursive: true, ..PathOptions::default() };
+        let expanded = expand_paths(std::slice::from_ref(&dir), &options);
+        assert_eq!(names(&dir, &expanded.paths), ["a/b/d.png", "a/c.png", "b.png"]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn max_count_applies_after_sorting() {
+        let dir = temp_dir("max", &["frame10.png", "frame2.png", "frame1.png"]);
+        let args: Vec<PathBuf> = ["frame10.png", "frame2.png", "frame1.png"].iter().map(|name| dir.join(name)).collect();
+        let options = PathOptions { max_count: Some(2), sort: Some(SortOrder::Natural), ..PathOptions::default() };
+        let expanded = expand_paths(&args, &options);
+        assert_eq!(names(&dir, &expanded.paths), ["frame1.png", "frame2.png"]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn non_image_files_are_skipped() {
+        let dir = temp_dir("skip", &["a.png", "notes.txt", "b.png"]);
+
+        let expanded = expand_paths(std::slice::from_ref(&dir), &PathOptions::default());
+        assert_eq!(names(&dir, &expanded.paths), ["a.png", "b.png"]);
+        assert_eq!(expanded.skipped, 1);
+
+        let expanded = expand_paths(&[dir.join("*")], &PathOptions::default());
+        assert_eq!(names(&dir, &expanded.paths), ["a.png", "b.png"]);
+        assert_eq!(expanded.skipped, 1);
+
+        // files given explicitly are kept, so the reason they can't be opened is reported
+        let expanded = expand_paths(&[dir.join("notes.txt")], &PathOptions::default());
+        assert_eq!(names(&dir, &expanded.paths), ["notes.txt"]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,273 @@
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::io::{Read, Write};
+use std::path::*;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::ipc::{create_private_dir, user_temp_dir};
+use super::wake;
+
+pub fn is_url(path: &str) -> bool {
+    path.starts_with("http://") || path.starts_with("https://")
+}
+
+/// `$XDG_CACHE_HOME/rim` if set, otherwise `<temp dir>/rim-cache-$USER`. Downloads are only written
+/// once it is created private with `create_cache_dir`, so other users can't replace the images shown.
+pub fn cache_dir() -> PathBuf {
+    match std::env::var_os("XDG_CACHE_HOME").filter(|dir| !dir.is_empty()) {
+        Some(dir) => PathBuf::from(dir).join("rim"),
+        None => user_temp_dir("rim-cache"),
+    }
+}
+
+pub fn create_cache_dir() -> std::io::Result<()> {
+    let dir = cache_dir();
+    if let Some(parent) = dir.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    create_private_dir(&dir)
+}
+
+/// Downloads are cached in the `cache_dir`, one file per URL.
+/// The extension of the URL is kept so the image format can be detected.
+pub fn cache_path(url: &str) -> PathBuf {
+    let mut hasher = DefaultHasher::new();
+    url.hash(&mut hasher);
+
+    let url_path = url.split(['?', '#']).next().unwrap_or(url);
+    let extension = match Path::new(url_path).extension().and_then(|ext| ext.to_str()) {
+        Some(ext) if ext.len() <= 4 => ext.to_lowercase(),
+        _ => "img".to_owned(),
+    };
+
+    cache_dir().join(format!("{:016x}.{}", hasher.finish(), extension))
+}
+
+/// The file an image is read from, the cached download for URLs and `path` itself otherwise.
+pub fn local_file(path: &Path) -> PathBuf {
+    match path.to_str() {
+        Some(url) if is_url(url) => cache_path(url),
+        _ => path.to_owned(),
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum FetchResult {
+    Updated,
+    NotModified,
+}
+
+/// Validators of the last response, sent with the next request so the server
+/// can answer with `304 Not Modified`.
+#[derive(Default)]
+pub struct Validators {
+    etag            : Option<String>,
+    last_modified   : Option<String>,
+}
+
+/// Downloads `url` to `path` unless the server reports it unchanged since the last fetch.
+/// The file is replaced atomically, so readers never see a partial download. The directory must exist.
+pub fn fetch(url: &str, path: &Path, validators: &mut Validators) -> Result<FetchResult, String> {
+    let mut request = ureq::get(url);
+    request.timeout(Duration::from_secs(30));
+    if let Some(etag) = &validators.etag {
+        request.set("If-None-Match", etag);
+    }
+    if let Some(last_modified) = &validators.last_modified {
+        request.set("If-Modified-Since", last_modified);
+    }
+
+    let response = request.call();
+    if let Some(err) = response.synthetic_error() {
+        return Err(format!("Failed to fetch {}: {}", url, err));
+    }
+    if response.status() == 304 {
+        return Ok(FetchResult::NotModified);
+    }
+    if !response.ok() {
+        return Err(format!("Failed to fetch {}: {}", url, response.status_line()));
+    }
+
+    validators.etag = response.header("ETag").map(str::to_owned);
+    validators.last_modified = response.header("Last-Modified").map(str::to_owned);
+
+    let mut data = Vec::new();
+    if let Err(err) = response.into_reader().read_to_end(&mut data) {
+        return Err(format!("Failed to fetch {}: {}", url, err));
+    }
+
+    let write = || -> std::io::Result<()> {
+        // a new file, never one left behind or a link to somewhere else
+        let partial = path.with_extension("part");
+        if let Err(err) = fs::remove_file(&partial) {
+            if err.kind() != std::io::ErrorKind::NotFound {
+                return Err(err);
+            }
+        }
+        fs::OpenOptions::new().write(true).create_new(true).open(&partial)?.write_all(&data)?;
+        fs::rename(&partial, path)
+    };
+    match write() {
+        Ok(_) => Ok(FetchResult::Updated),
+        Err(err) => Err(format!("Failed to write {:?}: {}", path, err)),
+    }
+}
+
+pub enum RemoteEvent {
+    /// The content of `url` changed and was written to its `cache_path`.
+    Downloaded { url: String },
+    Failed { url: String, msg: String },
+}
+
+/// Stops the download thread of a URL when dropped.
+pub struct RemoteHandle {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl Drop for RemoteHandle {
+    fn drop(&mut self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Fetches URLs on background threads, the network equivalent of the file watcher.
+pub struct RemoteImages {
+    sender  : mpsc::Sender<RemoteEvent>,
+    receiver: mpsc::Receiver<RemoteEvent>,
+}
+
+impl RemoteImages {
+    pub fn new() -> RemoteImages {
+        let (sender, receiver) = mpsc::channel();
+        RemoteImages { sender, receiver }
+    }
+
+    /// Downloads `url` once, or with `poll_interval` keeps checking it for changes
+    /// until the returned handle is dropped.
+    pub fn fetch(&self, url: &str, poll_interval: Option<Duration>) -> RemoteHandle {
+        let cancelled = Arc::new(AtomicBool::new(false));
+
+        let url = url.to_owned();
+        let sender = self.sender.clone();
+        let thread_cancelled = cancelled.clone();
+        std::thread::spawn(move || {
+            if let Err(err) = create_cache_dir() {
+                let msg = format!("Failed to create the download cache {:?}: {}", cache_dir(), err);
+                if sender.send(RemoteEvent::Failed { url, msg }).is_ok() {
+                    wake::wake();
+                }
+                return;
+            }
+
+            let path = cache_path(&url);
+            let mut validators = Validators::default();
+
+            while !thread_cancelled.load(Ordering::Relaxed) {
+                let event = match fetch(&url, &path, &mut validators) {
+                    Ok(FetchResult::Updated) => Some(RemoteEvent::Downloaded { url: url.clone() }),
+                    Ok(FetchResult::NotModified) => None,
+                    Err(msg) => Some(RemoteEvent::Failed { url: url.clone(), msg }),
+                };
+                if let Some(event) = event {
+                    if sender.send(event).is_err() {
+                        return;
+                    }
+                    wake::wake();
+                }
+
+                match poll_interval {
+                    Some(interval) => std::thread::sleep(interval),
+                    None => return,
+                }
+            }
+        });
+
+        RemoteHandle { cancelled }
+    }
+
+    pub fn try_recv(&self) -> Option<RemoteEvent> {
+        self.receiver.try_recv().ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+
+    /// Serves `body` with an ETag and answers conditional requests with 304.
+    fn serve(body: &'static [u8], requests: usize) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/image.png", listener.local_addr().unwrap());
+
+        std::thread::spawn(move || {
+            for stream in listener.incoming().take(requests) {
+                let mut stream = stream.unwrap();
+                let mut not_modified = false;
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line.trim().is_empty() {
+                        break;
+                    }
+                    not_modified |= line.to_lowercase().starts_with("if-none-match: \"v1\"");
+                }
+
+                if not_modified {
+                    write!(stream, "HTTP/1.1 304 Not Modified\r\nETag: \"v1\"\r\nContent-Length: 0\r\n\r\n").unwrap();
+                } else {
+                    write!(stream, "HTTP/1.1 200 OK\r\nETag: \"v1\"\r\nContent-Length: {}\r\n\r\n", body.len()).unwrap();
+                    stream.write_all(body).unwrap();
+                }
+            }
+        });
+
+        url
+    }
+
+    #[test]
+    fn fetch_uses_etag() {
+        let body = include_bytes!("../../test/test1.png");
+        let url = serve(body, 2);
+        let path = std::env::temp_dir().join("rim-test-fetch-uses-etag.png");
+        let mut validators = Validators::default();
+
+        assert_eq!(fetch(&url, &path, &mut validators), Ok(FetchResult::Updated));
+        assert_eq!(fs::read(&path).unwrap(), &body[..]);
+        assert_eq!(fetch(&url, &path, &mut validators), Ok(FetchResult::NotModified));
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn fetch_replaces_link_at_partial_file() {
+        let body = include_bytes!("../../test/test1.png");
+        let url = serve(body, 1);
+        let dir = std::env::temp_dir().join(format!("rim-test-fetch-link-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let target = dir.join("target");
+        fs::write(&target, "unchanged").unwrap();
+        let path = dir.join("image.png");
+        std::os::unix::fs::symlink(&target, path.with_extension("part")).unwrap();
+
+        assert_eq!(fetch(&url, &path, &mut Validators::default()), Ok(FetchResult::Updated));
+        assert_eq!(fs::read(&path).unwrap(), &body[..]);
+        assert_eq!(fs::read_to_string(&target).unwrap(), "unchanged");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn cache_path_keeps_extension() {
+        assert_eq!(cache_path("http://host/a/b.PNG?x=1").extension().unwrap(), "png");
+        assert_eq!(cache_path("http://host/render").extension().unwrap(), "img");
+        assert_ne!(cache_path("http://host/a.png"), cache_path("http://host/b.png"));
+    }
+}
@@ -0,0 +1,71 @@
+use std::cell::RefCell;
+
+use super::error::RimError;
+
+/// How a texture is sampled when shown smaller (minification) or larger (magnification) than it is.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum TextureFilter {
+    Nearest,
+    Linear,
+    /// Linear within and between mipmap levels, only for minification.
+    LinearMipmapLinear,
+}
+
+/// Creates and updates textures and draws the frames built with imgui. Textures are identified by the id
+/// passed to `imgui::Image`, pixels are always RGBA8.
+///
+/// Like a GL context, the renderer is current on the thread which draws, see `set_current` and `with`.
+/// Images create and delete their textures through the current renderer, so they must not outlive it.
+pub trait Renderer {
+    /// Creates an empty texture with nearest filtering, clamped to its edges.
+    fn create_texture(&mut self) -> Result<usize, RimError>;
+
+    /// Replaces a mipmap level of the texture, level 0 is the full resolution.
+    fn upload_texture(&mut self, texture: usize, level: u32, pixels: &image::RgbaImage);
+
+    /// Fills the mipmap levels below level 0, each scaled down from the one before.
+    fn generate_mipmaps(&mut self, texture: usize);
+
+    /// Reads level 0 of the texture back.
+    fn read_texture(&mut self, texture: usize) -> image::RgbaImage;
+
+    fn set_filter(&mut self, texture: usize, min_filter: TextureFilter, mag_filter: TextureFilter);
+
+    fn delete_texture(&mut self, texture: usize);
+
+    /// Width and height of the largest texture which can be created.
+    fn max_texture_size(&mut self) -> u32;
+
+    /// Clears the frame with `clear_color` and draws everything built with `ui` on top.
+    fn render(&mut self, ui: imgui::Ui, clear_color: [f32; 4]);
+
+    /// Reads the last frame drawn by `render` back, top row first.
+    fn read_frame(&mut self) -> image::RgbaImage;
+}
+
+thread_local! {
+    static CURRENT: RefCell<Option<Box<dyn Renderer>>> = RefCell::new(None);
+}
+
+/// Makes `renderer` the one used on this thread, dropping the one before. `None` drops the current renderer,
+/// e.g. before the GL context it belongs to is destroyed.
+pub fn set_current(renderer: Option<Box<dyn Renderer>>) {
+    CURRENT.with(|current| *current.borrow_mut() = renderer);
+}
+
+pub fn has_current() -> bool {
+    CURRENT.with(|current| current.borrow().is_some())
+}
+
+/// Calls `f` with the current renderer of this thread.
+///
+/// Panics if there is none, or if called again from within `f`.
+pub fn with<R>(f: impl FnOnce(&mut dyn Renderer) -> R) -> R {
+    CURRENT.with(|current| {
+        let mut current = current.borrow_mut();
+        match current.as_mut() {
+            Some(renderer) => f(renderer.as_mut()),
+            None => panic!("No renderer is current on this thread"),
+        }
+    })
+}
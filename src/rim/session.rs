@@ -0,0 +1,232 @@
+use std::fs;
+use std::path::*;
+
+use serde::{Deserialize, Serialize};
+
+use super::config::config_dir;
+use super::layout::{LayoutDirection, LayoutKind};
+use super::split_layout::SplitNode;
+use super::view::FilterMethod;
+
+/// Everything needed to restore the open images and their state, stored as TOML.
+#[derive(Serialize, Deserialize)]
+pub struct Session {
+    /// Index of the group which is shown.
+    #[serde(default)]
+    pub group   : usize,
+    pub window  : WindowState,
+    #[serde(default)]
+    pub groups  : Vec<GroupState>,
+
+    // sessions saved before there were groups store the views of their only group here, see `load`
+    #[serde(default, skip_serializing)]
+    selected    : usize,
+    #[serde(default, skip_serializing)]
+    layout      : Option<LayoutState>,
+    #[serde(default, skip_serializing)]
+    views       : Vec<ViewState>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct WindowState {
+    pub width   : u32,
+    pub height  : u32,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct GroupState {
+    pub name    : String,
+    /// Index of the selected view within the group.
+    #[serde(default)]
+    pub selected: usize,
+    #[serde(default)]
+    pub layout  : LayoutState,
+    // an empty list would be written as a value after the layout table, which TOML doesn't allow
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub views   : Vec<ViewState>,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct LayoutState {
+    #[serde(default)]
+    pub kind        : LayoutKind,
+    /// `None` means the direction is chosen automatically.
+    pub direction   : Option<LayoutDirection>,
+    /// Panes of the split layout, also stored while another layout is used.
+    #[serde(default)]
+    pub split       : Option<SplitNode>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ViewState {
+    pub path            : PathBuf,
+    pub zoom            : f32,
+    pub rect_pos        : [f32; 2],
+    pub filter_method   : FilterMethod,
+    pub history         : bool,
+}
+
+impl Session {
+    pub fn new(group: usize, window: WindowState, groups: Vec<GroupState>) -> Session {
+        Session {
+            group,
+            window,
+            groups,
+            selected    : 0,
+            layout      : None,
+            views       : Vec::new(),
+        }
+    }
+
+    /// Default location of the session file, `<config dir>/rim/session.toml`.
+    pub fn default_path() -> Option<PathBuf> {
+        config_dir().map(|dir| dir.join("rim").join("session.toml"))
+    }
+
+    pub fn load(path: &Path) -> Result<Session, String> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) => return Err(format!("Failed to read session file {:?}: {}", path, err)),
+        };
+
+        let mut session: Session = match toml::from_str(&text) {
+            Ok(session) => session,
+            Err(err) => return Err(format!("Failed to parse session file {:?}: {}", path, err)),
+        };
+
+        // older sessions have a single group
+        if session.groups.is_empty() && (session.layout.is_some() || !session.views.is_empty()) {
+            session.groups.push(GroupState {
+                name    : "Group 1".to_owned(),
+                selected: session.selected,
+                layout  : session.layout.take().unwrap_or_default(),
+                views   : std::mem::take(&mut session.views),
+            });
+        }
+        Ok(session)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let text = match toml::to_string_pretty(self) {
+            Ok(text) => text,
+            Err(err) => return Err(format!("Failed to serialize session: {}", err)),
+        };
+
+        if let Some(dir) = path.parent() {
+            if let Err(err) = fs::create_dir_all(dir) {
+                return Err(format!("Failed to create directory {:?}: {}", dir, err));
+            }
+        }
+
+        match fs::write(path, text) {
+            Ok(_) => Ok(()),
+            Err(err) => Err(format!("Failed to write session file {:?}: {}", path, err)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("rim-session-test-{}-{}.toml", name, std::process::id()))
+    }
+
+    fn view(path: &str, zoom: f32) -> ViewState {
+        ViewState {
+            path            : PathBuf::from(path),
+            zoom,
+            rect_pos        : [0.25, 0.5],
+            filter_method   : FilterMethod::Nearest,
+            history         : true,
+        }
+    }
+
+    #[test]
+    fn groups_round_trip() {
+        let path = temp_path("round-trip");
+        let groups = vec![
+            GroupState {
+                name    : "Empty".to_owned(),
+                selected: 0,
+                layout  : LayoutState::default(),
+                views   : Vec::new(),
+            },
+            GroupState {
+                name    : "Comparison".to_owned(),
+                selected: 1,
+                layout  : LayoutState {
+                    kind     : LayoutKind::Split,
+                    direction: Some(LayoutDirection::Horizontal),
+                    split    : None,
+                },
+                views   : vec![view("/images/a.png", 2.0), view("/images/b.png", 0.5)],
+            },
+        ];
+        Session::new(1, WindowState { width: 800, height: 600 }, groups).save(&path).unwrap();
+        let session = Session::load(&path);
+        fs::remove_file(&path).unwrap();
+        let session = session.unwrap();
+
+        assert_eq!(session.group, 1);
+        assert_eq!((session.window.width, session.window.height), (800, 600));
+        assert_eq!(session.groups.len(), 2);
+        assert_eq!(session.groups[0].name, "Empty");
+        assert!(session.groups[0].views.is_empty());
+
+        let group = &session.groups[1];
+        assert_eq!(group.name, "Comparison");
+        assert_eq!(group.selected, 1);
+        assert_eq!(group.layout.kind, LayoutKind::Split);
+        assert_eq!(group.layout.direction, Some(LayoutDirection::Horizontal));
+        assert_eq!(group.views.len(), 2);
+        assert_eq!(group.views[1].path, Path::new("/images/b.png"));
+        assert_eq!(group.views[1].zoom, 0.5);
+        assert_eq!(group.views[1].rect_pos, [0.25, 0.5]);
+        assert_eq!(group.views[1].filter_method, FilterMethod::Nearest);
+        assert!(group.views[1].history);
+    }
+
+    #[test]
+    fn single_group_sessions_still_load() {
+        let path = temp_path("legacy");
+        fs::write(&path, r#"
+            selected = 1
+
+            [window]
+            width = 640
+            height = 480
+
+            [layout]
+            kind = "rows"
+            direction = "vertical"
+
+            [[views]]
+            path = "/images/a.png"
+            zoom = 1.0
+            rect_pos = [0.0, 0.0]
+            filter_method = "linear"
+            history = false
+
+            [[views]]
+            path = "/images/b.png"
+            zoom = 1.0
+            rect_pos = [0.0, 0.0]
+            filter_method = "linear"
+            history = false
+        "#).unwrap();
+        let session = Session::load(&path);
+        fs::remove_file(&path).unwrap();
+        let session = session.unwrap();
+
+        assert_eq!(session.group, 0);
+        assert_eq!(session.groups.len(), 1);
+        let group = &session.groups[0];
+        assert_eq!(group.selected, 1);
+        assert_eq!(group.layout.kind, LayoutKind::Rows);
+        assert_eq!(group.layout.direction, Some(LayoutDirection::Vertical));
+        assert_eq!(group.views.len(), 2);
+        assert_eq!(group.views[0].path, Path::new("/images/a.png"));
+    }
+}
@@ -0,0 +1,322 @@
+use std::collections::HashMap;
+
+use imgui::{DrawCmd, DrawCmdParams};
+
+use super::error::RimError;
+use super::renderer::{Renderer, TextureFilter};
+use super::vec::Vec2;
+
+/// Largest texture, unless set with `set_max_texture_size`.
+const DEFAULT_MAX_TEXTURE_SIZE: u32 = 16384;
+
+struct Texture {
+    /// Mipmap levels, level 0 is the full resolution. Empty until something is uploaded.
+    levels      : Vec<image::RgbaImage>,
+    min_filter  : TextureFilter,
+    mag_filter  : TextureFilter,
+}
+
+#[derive(Copy, Clone)]
+struct Vertex {
+    pos     : Vec2,
+    uv      : Vec2,
+    color   : [f32; 4],
+}
+
+/// Draws frames on the CPU into an image, without a window or GPU, e.g. for screenshot tests.
+/// Samples and blends like the OpenGL renderer closely enough to compare frames with a small tolerance,
+/// but isn't pixel exact: minification uses the nearest mipmap level instead of blending two.
+pub struct SoftwareRenderer {
+    textures        : HashMap<usize, Texture>,
+    next_texture    : usize,
+    max_texture_size: u32,
+    frame           : image::RgbaImage,
+}
+
+impl SoftwareRenderer {
+    /// Creates the font texture of `imgui`, which must not change afterwards.
+    pub fn new(imgui: &mut imgui::Context) -> SoftwareRenderer {
+        let mut renderer = SoftwareRenderer {
+            textures        : HashMap::new(),
+            next_texture    : 1,
+            max_texture_size: DEFAULT_MAX_TEXTURE_SIZE,
+            frame           : image::RgbaImage::new(1, 1),
+        };
+
+        let mut fonts = imgui.fonts();
+        let font_pixels = {
+            let atlas = fonts.build_rgba32_texture();
+            image::RgbaImage::from_raw(atlas.width, atlas.height, atlas.data.to_vec())
+                .unwrap_or_else(|| image::RgbaImage::new(1, 1))
+        };
+        let font_texture = renderer.new_texture();
+        renderer.upload_texture(font_texture, 0, &font_pixels);
+        fonts.tex_id = imgui::TextureId::from(font_texture);
+
+        renderer
+    }
+
+    /// Smaller sizes make images use tiles without needing huge test images.
+    pub fn set_max_texture_size(&mut self, size: u32) {
+        self.max_texture_size = size;
+    }
+
+    fn new_texture(&mut self) -> usize {
+        let id = self.next_texture;
+        self.next_texture += 1;
+        self.textures.insert(id, Texture {
+            levels      : Vec::new(),
+            min_filter  : TextureFilter::Nearest,
+            mag_filter  : TextureFilter::Nearest,
+        });
+        id
+    }
+}
+
+impl Renderer for SoftwareRenderer {
+    fn create_texture(&mut self) -> Result<usize, RimError> {
+        Ok(self.new_texture())
+    }
+
+    fn upload_texture(&mut self, texture: usize, level: u32, pixels: &image::RgbaImage) {
+        if let Some(texture) = self.textures.get_mut(&texture) {
+            let level = level as usize;
+            if level <= texture.levels.len() {
+                texture.levels.truncate(level);
+                texture.levels.push(pixels.clone());
+            }
+        }
+    }
+
+    fn generate_mipmaps(&mut self, texture: usize) {
+        if let Some(texture) = self.textures.get_mut(&texture) {
+            texture.levels.truncate(1);
+            while let Some(level) = texture.levels.last() {
+                if level.width() == 1 && level.height() == 1 {
+                    break;
+                }
+                let width = std::cmp::max(1, level.width() / 2);
+                let height = std::cmp::max(1, level.height() / 2);
+                let next = image::imageops::resize(level, width, height, image::imageops::FilterType::Triangle);
+                texture.levels.push(next);
+            }
+        }
+    }
+
+    fn read_texture(&mut self, texture: usize) -> image::RgbaImage {
+        match self.textures.get(&texture).and_then(|texture| texture.levels.first()) {
+            Some(pixels) => pixels.clone(),
+            None => image::RgbaImage::new(1, 1),
+        }
+    }
+
+    fn set_filter(&mut self, texture: usize, min_filter: TextureFilter, mag_filter: TextureFilter) {
+        if let Some(texture) = self.textures.get_mut(&texture) {
+            texture.min_filter = min_filter;
+            texture.mag_filter = mag_filter;
+        }
+    }
+
+    fn delete_texture(&mut self, texture: usize) {
+        self.textures.remove(&texture);
+    }
+
+    fn max_texture_size(&mut self) -> u32 {
+        self.max_texture_size
+    }
+
+    fn render(&mut self, ui: imgui::Ui, clear_color: [f32; 4]) {
+        let draw_data = ui.render();
+        let display_pos = Vec2::from(draw_data.display_pos);
+        let scale = Vec2::from(draw_data.framebuffer_scale);
+        let size = Vec2::from(draw_data.display_size) * scale;
+
+        let clear_color = image::Rgba([
+            to_u8(clear_color[0]), to_u8(clear_color[1]), to_u8(clear_color[2]), to_u8(clear_color[3]),
+        ]);
+        self.frame = image::RgbaImage::from_pixel(size.x.round().max(1.0) as u32, size.y.round().max(1.0) as u32, clear_color);
+
+        for draw_list in draw_data.draw_lists() {
+            let vertices = draw_list.vtx_buffer();
+            let indices = draw_list.idx_buffer();
+            let mut offset = 0;
+
+            for command in draw_list.commands() {
+                if let DrawCmd::Elements { count, cmd_params: DrawCmdParams { clip_rect, texture_id, .. } } = command {
+                    let clip_min = (Vec2::new(clip_rect[0], clip_rect[1]) - display_pos) * scale;
+                    let clip_max = (Vec2::new(clip_rect[2], clip_rect[3]) - display_pos) * scale;
+                    let texture = self.textures.get(&texture_id.id());
+
+                    for triangle in indices[offset..offset + count].chunks_exact(3) {
+                        let vertex = |index: imgui::DrawIdx| {
+                            let vertex = &vertices[index as usize];
+                            Vertex {
+                                pos     : (Vec2::from(vertex.pos) - display_pos) * scale,
+                                uv      : Vec2::from(vertex.uv),
+                                color   : [
+                                    vertex.col[0] as f32 / 255.0, vertex.col[1] as f32 / 255.0,
+                                    vertex.col[2] as f32 / 255.0, vertex.col[3] as f32 / 255.0,
+                                ],
+                            }
+                        };
+                        draw_triangle(
+                            &mut self.frame,
+                            texture,
+                            [vertex(triangle[0]), vertex(triangle[1]), vertex(triangle[2])],
+                            clip_min,
+                            clip_max);
+                    }
+                    offset += count;
+                }
+            }
+        }
+    }
+
+    fn read_frame(&mut self) -> image::RgbaImage {
+        self.frame.clone()
+    }
+}
+
+impl Texture {
+    /// Samples the texture at `uv`, `footprint` is the number of texels covered by one pixel.
+    /// Textures without content are black, like incomplete textures in OpenGL.
+    fn sample(&self, uv: Vec2, footprint: f32) -> [f32; 4] {
+        let (filter, level) = if footprint > 1.0 {
+            match self.min_filter {
+                TextureFilter::LinearMipmapLinear => {
+                    let level = footprint.log2().round() as usize;
+                    (TextureFilter::Linear, std::cmp::min(level, self.levels.len().saturating_sub(1)))
+                },
+                filter => (filter, 0),
+            }
+        } else {
+            (self.mag_filter, 0)
+        };
+
+        let pixels = match self.levels.get(level) {
+            Some(pixels) => pixels,
+            None => return [0.0, 0.0, 0.0, 1.0],
+        };
+        let x = uv.x * pixels.width() as f32;
+        let y = uv.y * pixels.height() as f32;
+
+        match filter {
+            TextureFilter::Nearest => texel(pixels, x.floor() as i64, y.floor() as i64),
+            _ => {
+                let (x, y) = (x - 0.5, y - 0.5);
+                let (x0, y0) = (x.floor(), y.floor());
+                let (fx, fy) = (x - x0, y - y0);
+                let (x0, y0) = (x0 as i64, y0 as i64);
+
+                let top = mix(texel(pixels, x0, y0), texel(pixels, x0 + 1, y0), fx);
+                let bottom = mix(texel(pixels, x0, y0 + 1), texel(pixels, x0 + 1, y0 + 1), fx);
+                mix(top, bottom, fy)
+            },
+        }
+    }
+}
+
+/// Fills the pixels whose centers are inside the triangle and the clip rectangle, blended like
+/// `GL_SRC_ALPHA, GL_ONE_MINUS_SRC_ALPHA`. Edges shared by two triangles are only filled once.
+fn draw_triangle(frame: &mut image::RgbaImage, texture: Option<&Texture>, mut v: [Vertex; 3], clip_min: Vec2, clip_max: Vec2) {
+    let mut area = edge(v[0].pos, v[1].pos, v[2].pos);
+    if area == 0.0 {
+        return;
+    }
+    if area < 0.0 {
+        v.swap(1, 2);
+        area = -area;
+    }
+
+    let min_x = v.iter().map(|v| v.pos.x).fold(f32::MAX, f32::min).max(clip_min.x).floor().max(0.0) as u32;
+    let min_y = v.iter().map(|v| v.pos.y).fold(f32::MAX, f32::min).max(clip_min.y).floor().max(0.0) as u32;
+    let max_x = v.iter().map(|v| v.pos.x).fold(f32::MIN, f32::max).min(clip_max.x).ceil().min(frame.width() as f32);
+    let max_y = v.iter().map(|v| v.pos.y).fold(f32::MIN, f32::max).min(clip_max.y).ceil().min(frame.height() as f32);
+    let (max_x, max_y) = (max_x.max(0.0) as u32, max_y.max(0.0) as u32);
+
+    // barycentric weights of a point, the mapping is affine so texels per pixel are the same everywhere
+    let weights = |p: Vec2| [
+        edge(v[1].pos, v[2].pos, p) / area,
+        edge(v[2].pos, v[0].pos, p) / area,
+        edge(v[0].pos, v[1].pos, p) / area,
+    ];
+    let uv_at = |w: [f32; 3]| v[0].uv * w[0] + v[1].uv * w[1] + v[2].uv * w[2];
+    let footprint = match texture.and_then(|texture| texture.levels.first()) {
+        Some(pixels) => {
+            let texture_size = Vec2::new(pixels.width() as f32, pixels.height() as f32);
+            let origin = uv_at(weights(Vec2::zero()));
+            let dx = (uv_at(weights(Vec2::new(1.0, 0.0))) - origin) * texture_size;
+            let dy = (uv_at(weights(Vec2::new(0.0, 1.0))) - origin) * texture_size;
+            (dx.x * dx.x + dx.y * dx.y).sqrt().max((dy.x * dy.x + dy.y * dy.y).sqrt())
+        },
+        None => 1.0,
+    };
+    let edges = [(v[1].pos, v[2].pos), (v[2].pos, v[0].pos), (v[0].pos, v[1].pos)];
+
+    for y in min_y..max_y {
+        for x in min_x..max_x {
+            let p = Vec2::new(x as f32 + 0.5, y as f32 + 0.5);
+            if p.x < clip_min.x || p.y < clip_min.y || p.x >= clip_max.x || p.y >= clip_max.y {
+                continue;
+            }
+
+            let w = weights(p);
+            let inside = w.iter().zip(edges.iter())
+                .all(|(w, (a, b))| *w > 0.0 || (*w == 0.0 && owns_edge(*a, *b)));
+            if !inside {
+                continue;
+            }
+
+            let mut color = [0.0; 4];
+            for (i, channel) in color.iter_mut().enumerate() {
+                *channel = v[0].color[i] * w[0] + v[1].color[i] * w[1] + v[2].color[i] * w[2];
+            }
+            let texel = match texture {
+                Some(texture) => texture.sample(uv_at(w), footprint),
+                None => [1.0; 4],
+            };
+            for (channel, texel) in color.iter_mut().zip(texel.iter()) {
+                *channel *= texel;
+            }
+
+            let pixel = frame.get_pixel_mut(x, y);
+            let alpha = color[3];
+            for (i, channel) in pixel.0.iter_mut().enumerate() {
+                let dst = *channel as f32 / 255.0;
+                *channel = to_u8(color[i] * alpha + dst * (1.0 - alpha));
+            }
+        }
+    }
+}
+
+/// Twice the signed area of the triangle `a`, `b`, `p`, positive if `p` is on the inner side of the edge from `a` to `b`.
+fn edge(a: Vec2, b: Vec2, p: Vec2) -> f32 {
+    (b.x - a.x) * (p.y - a.y) - (b.y - a.y) * (p.x - a.x)
+}
+
+/// Of the two triangles sharing an edge, which run along it in opposite directions, only one owns it.
+fn owns_edge(a: Vec2, b: Vec2) -> bool {
+    b.y > a.y || (b.y == a.y && b.x < a.x)
+}
+
+/// The texel at `x`, `y`, clamped to the edge of the texture.
+fn texel(pixels: &image::RgbaImage, x: i64, y: i64) -> [f32; 4] {
+    let x = x.clamp(0, pixels.width() as i64 - 1) as u32;
+    let y = y.clamp(0, pixels.height() as i64 - 1) as u32;
+    let pixel = pixels.get_pixel(x, y);
+    [
+        pixel[0] as f32 / 255.0, pixel[1] as f32 / 255.0,
+        pixel[2] as f32 / 255.0, pixel[3] as f32 / 255.0,
+    ]
+}
+
+fn mix(a: [f32; 4], b: [f32; 4], t: f32) -> [f32; 4] {
+    [
+        a[0] + (b[0] - a[0]) * t, a[1] + (b[1] - a[1]) * t,
+        a[2] + (b[2] - a[2]) * t, a[3] + (b[3] - a[3]) * t,
+    ]
+}
+
+fn to_u8(value: f32) -> u8 {
+    (value.max(0.0).min(1.0) * 255.0).round() as u8
+}
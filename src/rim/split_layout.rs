@@ -0,0 +1,512 @@
+use serde::{Deserialize, Serialize};
+
+use super::layout::{self, Layout, LayoutDirection, Rect};
+
+/// Smallest share of a split a pane can be resized to.
+const MIN_SHARE: f32 = 0.05;
+
+/// Distance in pixels from a divider at which it can still be grabbed.
+const GRAB_DISTANCE: i32 = 4;
+
+/// A pane of the split layout, which either shows one view or is split into panes side by side or above each other.
+/// Stored in sessions, so views can be arranged again the same way.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SplitNode {
+    /// Share of the pane in its split, relative to the other panes of the split.
+    pub weight      : f32,
+    /// `Horizontal` places the panes side by side, only used if there are any.
+    pub direction   : LayoutDirection,
+    /// Panes without children show one view, views are assigned to them in order.
+    #[serde(default)]
+    pub children    : Vec<SplitNode>,
+}
+
+impl SplitNode {
+    fn leaf(weight: f32) -> SplitNode {
+        SplitNode {
+            weight,
+            direction   : LayoutDirection::Horizontal,
+            children    : Vec::new(),
+        }
+    }
+
+    fn is_leaf(&self) -> bool {
+        self.children.is_empty()
+    }
+
+    fn leaf_count(&self) -> usize {
+        if self.is_leaf() {
+            1
+        } else {
+            self.children.iter().map(SplitNode::leaf_count).sum()
+        }
+    }
+
+    /// Indices of the children leading from this node to the pane of view `index`.
+    fn leaf_path(&self, mut index: usize) -> Option<Vec<usize>> {
+        if self.is_leaf() {
+            return if index == 0 { Some(Vec::new()) } else { None };
+        }
+
+        for (child_index, child) in self.children.iter().enumerate() {
+            let count = child.leaf_count();
+            if index < count {
+                let mut path = vec![child_index];
+                path.extend(child.leaf_path(index)?);
+                return Some(path);
+            }
+            index -= count;
+        }
+        None
+    }
+
+    fn node_mut(&mut self, path: &[usize]) -> Option<&mut SplitNode> {
+        match path.split_first() {
+            Some((first, rest)) => self.children.get_mut(*first)?.node_mut(rest),
+            None => Some(self),
+        }
+    }
+
+    /// Weights which aren't positive numbers would break the layout, e.g. in an edited session file.
+    fn sanitize(&mut self) {
+        if !(self.weight.is_finite() && self.weight > 0.0) {
+            self.weight = 1.0;
+        }
+        for child in self.children.iter_mut() {
+            child.sanitize();
+        }
+    }
+
+    fn child_cells(&self, cell: Rect) -> Vec<Rect> {
+        let vertical = self.direction == LayoutDirection::Vertical;
+        let (start, size) = cell.span(vertical);
+        let weights: Vec<f32> = self.children.iter().map(|child| child.weight).collect();
+
+        layout::split(size, &weights).into_iter()
+            .map(|(offset, length)| if vertical {
+                Rect { x: cell.x, y: start + offset, width: cell.width, height: length }
+            } else {
+                Rect { x: start + offset, y: cell.y, width: length, height: cell.height }
+            })
+            .collect()
+    }
+
+    fn cells(&self, cell: Rect, cells: &mut Vec<Rect>) {
+        if self.is_leaf() {
+            cells.push(cell);
+            return;
+        }
+
+        for (child, child_cell) in self.children.iter().zip(self.child_cells(cell)) {
+            child.cells(child_cell, cells);
+        }
+    }
+
+    fn dividers(&self, cell: Rect, path: &mut Vec<usize>, dividers: &mut Vec<Divider>) {
+        let child_cells = self.child_cells(cell);
+        for (index, (child, child_cell)) in self.children.iter().zip(child_cells.iter()).enumerate() {
+            path.push(index);
+            child.dividers(*child_cell, path, dividers);
+            path.pop();
+        }
+
+        let vertical = self.direction == LayoutDirection::Vertical;
+        let (cross_start, cross_size) = cell.span(!vertical);
+        for (index, pair) in child_cells.windows(2).enumerate() {
+            let (before_start, _) = pair[0].span(vertical);
+            let (after_start, after_size) = pair[1].span(vertical);
+            dividers.push(Divider {
+                path        : path.clone(),
+                index,
+                direction   : self.direction,
+                position    : after_start,
+                start       : cross_start,
+                end         : cross_start + cross_size,
+                min         : before_start,
+                max         : after_start + after_size,
+            });
+        }
+    }
+}
+
+/// Boundary between two neighboring panes of a split, which resizes them when dragged.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Divider {
+    /// Path to the split and index of the pane before the divider.
+    path            : Vec<usize>,
+    index           : usize,
+    /// Direction of the split, the divider of a `Horizontal` split is a vertical line.
+    pub direction   : LayoutDirection,
+    /// Position of the divider along the split and the range it covers across the split.
+    pub position    : i32,
+    pub start       : i32,
+    pub end         : i32,
+    /// Start of the pane before and end of the pane after the divider, it moves between them.
+    min             : i32,
+    max             : i32,
+}
+
+/// Arranges the views in a tree of splits, like a tiling window manager. The panes are changed by the user instead
+/// of following the aspect ratios, and are kept when switching to other layouts and back.
+#[derive(Debug, Clone)]
+pub struct SplitLayout {
+    root    : SplitNode,
+}
+
+impl Default for SplitLayout {
+    fn default() -> SplitLayout {
+        SplitLayout {
+            root    : SplitNode::leaf(1.0),
+        }
+    }
+}
+
+impl SplitLayout {
+    pub fn new() -> Box<dyn Layout> {
+        Box::new(SplitLayout::default())
+    }
+
+    pub fn from_tree(mut root: SplitNode) -> SplitLayout {
+        root.sanitize();
+        SplitLayout {
+            root,
+        }
+    }
+
+    pub fn tree(&self) -> &SplitNode {
+        &self.root
+    }
+
+    /// Adds or removes panes at the end until there is one for each view. New panes are added next to the last one,
+    /// a single pane is split in `direction`.
+    pub fn fit(&mut self, view_count: usize, direction: LayoutDirection) {
+        let view_count = view_count.max(1);
+        while self.root.leaf_count() < view_count {
+            self.push(direction);
+        }
+        while self.root.leaf_count() > view_count {
+            self.remove(self.root.leaf_count() - 1);
+        }
+    }
+
+    fn push(&mut self, direction: LayoutDirection) {
+        let path = match self.root.leaf_path(self.root.leaf_count() - 1) {
+            Some(path) => path,
+            None => return,
+        };
+
+        match path.split_last() {
+            Some((index, parent_path)) => if let Some(parent) = self.root.node_mut(parent_path) {
+                let weight = parent.children.iter().map(|child| child.weight).sum::<f32>() / parent.children.len() as f32;
+                parent.children.insert(index + 1, SplitNode::leaf(weight));
+            },
+            None => self.root = SplitNode {
+                weight      : self.root.weight,
+                direction,
+                children    : vec![SplitNode::leaf(1.0), SplitNode::leaf(1.0)],
+            },
+        }
+    }
+
+    /// Removes the pane of view `index`, the other panes of its split take its space.
+    pub fn remove(&mut self, index: usize) {
+        let path = match self.root.leaf_path(index) {
+            Some(path) => path,
+            None => return,
+        };
+        let (child, parent_path) = match path.split_last() {
+            Some(last) => last,
+            // the last pane stays for the next view
+            None => return,
+        };
+
+        if let Some(parent) = self.root.node_mut(parent_path) {
+            parent.children.remove(*child);
+            if parent.children.len() == 1 {
+                let mut only = parent.children.remove(0);
+                only.weight = parent.weight;
+                *parent = only;
+            }
+        }
+    }
+
+    /// Splits the pane of view `index` in `direction` and moves the next view into the new pane, or the previous
+    /// one if `index` is the last view. Returns false if there is no other view.
+    pub fn split(&mut self, index: usize, direction: LayoutDirection) -> bool {
+        let count = self.root.leaf_count();
+        if index >= count || count < 2 {
+            return false;
+        }
+
+        // the views keep their order, which the panes are assigned in
+        let first = if index + 1 < count {
+            self.remove(index + 1);
+            index
+        } else {
+            self.remove(index - 1);
+            index - 1
+        };
+
+        let path = match self.root.leaf_path(first) {
+            Some(path) => path,
+            None => return false,
+        };
+        match self.root.node_mut(&path) {
+            Some(pane) => {
+                *pane = SplitNode {
+                    weight      : pane.weight,
+                    direction,
+                    children    : vec![SplitNode::leaf(1.0), SplitNode::leaf(1.0)],
+                };
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Replaces the split around the pane of view `index` by its panes, which join the split around it.
+    /// Returns false if the pane isn't in a nested split.
+    pub fn merge(&mut self, index: usize) -> bool {
+        let path = match self.root.leaf_path(index) {
+            Some(path) if path.len() >= 2 => path,
+            _ => return false,
+        };
+        let (split_index, outer_path) = match path[.. path.len() - 1].split_last() {
+            Some(last) => last,
+            None => return false,
+        };
+
+        let outer = match self.root.node_mut(outer_path) {
+            Some(outer) => outer,
+            None => return false,
+        };
+        let split = outer.children.remove(*split_index);
+        let total: f32 = split.children.iter().map(|child| child.weight).sum();
+        for (offset, mut child) in split.children.into_iter().enumerate() {
+            child.weight = child.weight / total * split.weight;
+            outer.children.insert(split_index + offset, child);
+        }
+        true
+    }
+
+    /// Grows the pane of view `index` by `amount` of its split, or shrinks it if negative.
+    /// The other panes of the split keep their proportions. Returns false if the pane isn't split.
+    pub fn resize(&mut self, index: usize, amount: f32) -> bool {
+        let path = match self.root.leaf_path(index) {
+            Some(path) => path,
+            None => return false,
+        };
+        let (child, parent_path) = match path.split_last() {
+            Some(last) => last,
+            None => return false,
+        };
+        let parent = match self.root.node_mut(parent_path) {
+            Some(parent) => parent,
+            None => return false,
+        };
+
+        let total: f32 = parent.children.iter().map(|child| child.weight).sum();
+        let others = parent.children.len() - 1;
+        let max_share = (1.0 - MIN_SHARE * others as f32).max(MIN_SHARE);
+        let share = (parent.children[*child].weight / total + amount).clamp(MIN_SHARE, max_share);
+        let others_total = total - parent.children[*child].weight;
+
+        for (index, pane) in parent.children.iter_mut().enumerate() {
+            pane.weight = if index == *child {
+                share
+            } else {
+                pane.weight / others_total * (1.0 - share)
+            };
+        }
+        true
+    }
+
+    /// Dividers between the panes in a window of this size.
+    pub fn dividers(&self, width: i32, height: i32) -> Vec<Divider> {
+        let mut dividers = Vec::new();
+        self.root.dividers(Rect { x: 0, y: 0, width, height }, &mut Vec::new(), &mut dividers);
+        dividers
+    }
+
+    /// The divider closest to `x`, `y` if it's close enough to grab.
+    pub fn divider_at(&self, x: i32, y: i32, width: i32, height: i32) -> Option<Divider> {
+        let distance = |divider: &Divider| match divider.direction {
+            LayoutDirection::Horizontal => ((x - divider.position).abs(), y),
+            LayoutDirection::Vertical => ((y - divider.position).abs(), x),
+        };
+
+        self.dividers(width, height).into_iter()
+            .filter(|divider| {
+                let (distance, across) = distance(divider);
+                distance <= GRAB_DISTANCE && across >= divider.start && across < divider.end
+            })
+            .min_by_key(|divider| distance(divider).0)
+    }
+
+    /// Moves `divider` to `position`, resizing the panes before and after it.
+    pub fn drag(&mut self, divider: &Divider, position: i32) {
+        let split = match self.root.node_mut(&divider.path) {
+            Some(split) if divider.index + 1 < split.children.len() => split,
+            _ => return,
+        };
+
+        let share = (position - divider.min) as f32 / (divider.max - divider.min).max(1) as f32;
+        let share = share.clamp(MIN_SHARE, 1.0 - MIN_SHARE);
+        let total = split.children[divider.index].weight + split.children[divider.index + 1].weight;
+        split.children[divider.index].weight = total * share;
+        split.children[divider.index + 1].weight = total * (1.0 - share);
+    }
+}
+
+impl Layout for SplitLayout {
+    /// `direction` is only used to split a single pane if there are more views than panes.
+    fn layout(&self, aspect_ratios: &[f32], _selected: usize, width: i32, height: i32, direction: LayoutDirection) -> Vec<Rect> {
+        if aspect_ratios.is_empty() {
+            return Vec::new();
+        }
+
+        let window = Rect { x: 0, y: 0, width, height };
+        let mut cells = Vec::with_capacity(aspect_ratios.len());
+        if self.root.leaf_count() == aspect_ratios.len() {
+            self.root.cells(window, &mut cells);
+        } else {
+            let mut fitted = self.clone();
+            fitted.fit(aspect_ratios.len(), direction);
+            fitted.root.cells(window, &mut cells);
+        }
+        cells
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(x: i32, y: i32, width: i32, height: i32) -> Rect {
+        Rect { x, y, width, height }
+    }
+
+    fn cells(layout: &SplitLayout, view_count: usize) -> Vec<Rect> {
+        layout.layout(&vec![1.0; view_count], 0, 600, 400, LayoutDirection::Horizontal)
+    }
+
+    /// One reference pane on the left, the others stacked on the right.
+    fn reference_layout() -> SplitLayout {
+        let mut layout = SplitLayout::default();
+        layout.fit(3, LayoutDirection::Horizontal);
+        assert!(layout.split(1, LayoutDirection::Vertical));
+        layout
+    }
+
+    #[test]
+    fn fit_adds_panes_next_to_the_last() {
+        let mut layout = SplitLayout::default();
+        layout.fit(3, LayoutDirection::Horizontal);
+        assert_eq!(cells(&layout, 3), vec![rect(0, 0, 200, 400), rect(200, 0, 200, 400), rect(400, 0, 200, 400)]);
+
+        layout.fit(1, LayoutDirection::Horizontal);
+        assert_eq!(layout.tree(), &SplitNode::leaf(1.0));
+    }
+
+    #[test]
+    fn split_moves_next_view_into_pane() {
+        let layout = reference_layout();
+        assert_eq!(cells(&layout, 3), vec![rect(0, 0, 300, 400), rect(300, 0, 300, 200), rect(300, 200, 300, 200)]);
+
+        // new views join the stack on the right
+        let mut layout = layout;
+        layout.fit(4, LayoutDirection::Horizontal);
+        assert_eq!(cells(&layout, 4)[3], rect(300, 267, 300, 133));
+    }
+
+    #[test]
+    fn split_last_view_moves_previous_view() {
+        let mut layout = SplitLayout::default();
+        layout.fit(2, LayoutDirection::Horizontal);
+        assert!(layout.split(1, LayoutDirection::Vertical));
+        assert_eq!(cells(&layout, 2), vec![rect(0, 0, 600, 200), rect(0, 200, 600, 200)]);
+
+        let mut single = SplitLayout::default();
+        assert!(!single.split(0, LayoutDirection::Vertical));
+    }
+
+    #[test]
+    fn merge_joins_outer_split() {
+        let mut layout = reference_layout();
+        assert!(!layout.merge(0));
+        assert!(layout.merge(2));
+        assert_eq!(cells(&layout, 3), vec![rect(0, 0, 300, 400), rect(300, 0, 150, 400), rect(450, 0, 150, 400)]);
+    }
+
+    #[test]
+    fn remove_gives_space_to_neighbors() {
+        let mut layout = reference_layout();
+        layout.remove(1);
+        assert_eq!(cells(&layout, 2), vec![rect(0, 0, 300, 400), rect(300, 0, 300, 400)]);
+
+        layout.remove(0);
+        layout.remove(0);
+        assert_eq!(cells(&layout, 1), vec![rect(0, 0, 600, 400)]);
+    }
+
+    #[test]
+    fn resize_keeps_other_proportions() {
+        let mut layout = SplitLayout::default();
+        layout.fit(3, LayoutDirection::Vertical);
+        assert!(layout.resize(0, 1.0 / 6.0));
+        let cells = layout.layout(&[1.0; 3], 0, 400, 600, LayoutDirection::Vertical);
+        assert_eq!(cells, vec![rect(0, 0, 400, 300), rect(0, 300, 400, 150), rect(0, 450, 400, 150)]);
+
+        // panes never disappear
+        assert!(layout.resize(1, -1.0));
+        assert!(layout.layout(&[1.0; 3], 0, 400, 600, LayoutDirection::Vertical)[1].height > 0);
+
+        assert!(!SplitLayout::default().resize(0, 0.1));
+    }
+
+    #[test]
+    fn drag_divider() {
+        let mut layout = reference_layout();
+        let dividers = layout.dividers(600, 400);
+        assert_eq!(dividers.len(), 2);
+
+        let divider = layout.divider_at(302, 100, 600, 400).unwrap();
+        assert_eq!((divider.direction, divider.position), (LayoutDirection::Horizontal, 300));
+        layout.drag(&divider, 450);
+        assert_eq!(cells(&layout, 3)[0], rect(0, 0, 450, 400));
+
+        // the divider between the stacked panes only covers the right side
+        let divider = layout.divider_at(500, 198, 600, 400).unwrap();
+        assert_eq!((divider.direction, divider.start, divider.end), (LayoutDirection::Vertical, 450, 600));
+        assert!(layout.divider_at(100, 200, 600, 400).is_none());
+
+        layout.drag(&divider, 1000);
+        assert_eq!(cells(&layout, 3)[2], rect(450, 380, 150, 20));
+    }
+
+    #[test]
+    fn fewer_views_than_panes() {
+        let layout = reference_layout();
+        assert_eq!(cells(&layout, 2), vec![rect(0, 0, 300, 400), rect(300, 0, 300, 400)]);
+        assert_eq!(cells(&layout, 5).len(), 5);
+    }
+
+    #[test]
+    fn tree_survives_session() {
+        #[derive(Serialize, Deserialize)]
+        struct State {
+            split: SplitNode,
+        }
+
+        let mut layout = reference_layout();
+        assert!(layout.resize(0, 0.1));
+
+        let text = toml::to_string_pretty(&State { split: layout.tree().clone() }).unwrap();
+        let state: State = toml::from_str(&text).unwrap();
+        assert_eq!(&state.split, layout.tree());
+
+        let mut broken = state.split;
+        broken.children[0].weight = -1.0;
+        assert_eq!(SplitLayout::from_tree(broken).tree().children[0].weight, 1.0);
+    }
+}
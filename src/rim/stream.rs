@@ -0,0 +1,259 @@
+use std::fs::File;
+use std::io;
+use std::io::{BufReader, Read};
+use std::path::*;
+use std::sync::mpsc;
+
+use super::wake;
+
+/// Larger frames are rejected, so a broken length in the stream can't make the reader allocate gigabytes.
+const MAX_FRAME_BYTES: usize = 256 * 1024 * 1024;
+
+/// How consecutive images are separated in a stream.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum FrameFormat {
+    /// PNG files written back to back, e.g. by `ffmpeg -f image2pipe -c:v png`.
+    Png,
+    /// Every frame is prefixed with its length in bytes as big endian u32, the frame itself
+    /// can be in any format supported by `image`.
+    LengthPrefixed,
+}
+
+impl FrameFormat {
+    pub fn from_name(name: &str) -> Option<FrameFormat> {
+        match name {
+            "png" => Some(FrameFormat::Png),
+            "length" => Some(FrameFormat::LengthPrefixed),
+            _ => None,
+        }
+    }
+}
+
+/// Reads encoded frames from stdin (`-`) or a named pipe on a background thread.
+/// A named pipe is reopened whenever the writer closes it, so a producer can be restarted.
+pub struct FrameStream {
+    pub path    : PathBuf,
+    receiver    : mpsc::Receiver<Vec<u8>>,
+}
+
+impl FrameStream {
+    pub fn open(path: &Path, format: FrameFormat) -> FrameStream {
+        let (sender, receiver) = mpsc::channel();
+
+        let thread_path = path.to_owned();
+        std::thread::spawn(move || read_frames(&thread_path, format, &sender));
+
+        FrameStream {
+            path: path.to_owned(),
+            receiver,
+        }
+    }
+
+    /// Returns the most recent frame, older frames which haven't been shown yet are dropped.
+    pub fn latest_frame(&self) -> Option<Vec<u8>> {
+        let mut latest = None;
+        while let Ok(frame) = self.receiver.try_recv() {
+            latest = Some(frame);
+        }
+        latest
+    }
+}
+
+pub fn is_stdin(path: &Path) -> bool {
+    path == Path::new("-")
+}
+
+#[cfg(unix)]
+pub fn is_fifo(path: &Path) -> bool {
+    use std::os::unix::fs::FileTypeExt;
+    match std::fs::metadata(path) {
+        Ok(metadata) => metadata.file_type().is_fifo(),
+        Err(_) => false,
+    }
+}
+
+#[cfg(not(unix))]
+pub fn is_fifo(_path: &Path) -> bool {
+    false
+}
+
+fn read_frames(path: &Path, format: FrameFormat, sender: &mpsc::Sender<Vec<u8>>) {
+    loop {
+        let reader: Box<dyn Read> = if is_stdin(path) {
+            Box::new(io::stdin())
+        } else {
+            // opening a named pipe blocks until there is a writer
+            match File::open(path) {
+                Ok(file) => Box::new(file),
+                Err(err) => {
+                    log::error!("Failed to open stream {:?}: {}", path, err);
+                    return;
+                },
+            }
+        };
+        let mut reader = BufReader::new(reader);
+
+        loop {
+            match read_frame(&mut reader, format) {
+                Ok(Some(frame)) => if sender.send(frame).is_err() {
+                    return;
+                } else {
+                    wake::wake();
+                },
+                Ok(None) => break,
+                Err(err) => {
+                    log::error!("Failed to read frame from {:?}: {}", path, err);
+                    break;
+                },
+            }
+        }
+
+        if !is_fifo(path) {
+            return;
+        }
+    }
+}
+
+/// Reads the next frame, or `None` if the stream ended before its first byte.
+fn read_frame(reader: &mut dyn Read, format: FrameFormat) -> io::Result<Option<Vec<u8>>> {
+    match format {
+        FrameFormat::LengthPrefixed => {
+            let mut length = [0u8; 4];
+            if !read_exact_or_eof(reader, &mut length)? {
+                return Ok(None);
+            }
+
+            let mut frame = Vec::new();
+            read_to_frame(reader, u32::from_be_bytes(length) as usize, &mut frame)?;
+            Ok(Some(frame))
+        },
+
+        FrameFormat::Png => {
+            const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+
+            let mut frame = vec![0u8; SIGNATURE.len()];
+            if !read_exact_or_eof(reader, &mut frame)? {
+                return Ok(None);
+            }
+            if frame[..] != SIGNATURE[..] {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, "Not a png stream"));
+            }
+
+            // chunks are length, type, data, crc, the last one is IEND
+            loop {
+                let mut header = [0u8; 8];
+                reader.read_exact(&mut header)?;
+                let length = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+                frame.extend_from_slice(&header);
+
+                read_to_frame(reader, length + 4, &mut frame)?;
+
+                if &header[4..] == b"IEND" {
+                    return Ok(Some(frame));
+                }
+            }
+        },
+    }
+}
+
+/// Appends the next `length` bytes to `frame`, which only grows as far as the stream has data.
+fn read_to_frame(reader: &mut dyn Read, length: usize, frame: &mut Vec<u8>) -> io::Result<()> {
+    if length > MAX_FRAME_BYTES - frame.len() {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("Frame is larger than {} bytes", MAX_FRAME_BYTES)));
+    }
+
+    let read = (&mut *reader).take(length as u64).read_to_end(frame)?;
+    if read < length {
+        return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "Stream ended in the middle of a frame"));
+    }
+    Ok(())
+}
+
+/// Like `read_exact`, but returns `false` instead of an error if the reader is at its end.
+fn read_exact_or_eof(reader: &mut dyn Read, buf: &mut [u8]) -> io::Result<bool> {
+    let mut read = 0;
+    while read < buf.len() {
+        match reader.read(&mut buf[read..]) {
+            Ok(0) if read == 0 => return Ok(false),
+            Ok(0) => return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "Stream ended in the middle of a frame")),
+            Ok(n) => read += n,
+            Err(ref err) if err.kind() == io::ErrorKind::Interrupted => {},
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PNG: &[u8] = include_bytes!("../../test/test1.png");
+
+    fn length_prefixed(frames: &[&[u8]]) -> Vec<u8> {
+        let mut stream = Vec::new();
+        for frame in frames.iter() {
+            stream.extend_from_slice(&(frame.len() as u32).to_be_bytes());
+            stream.extend_from_slice(frame);
+        }
+        stream
+    }
+
+    #[test]
+    fn length_prefixed_frames() {
+        let stream = length_prefixed(&[b"first", b"", b"third"]);
+        let mut reader = &stream[..];
+        assert_eq!(read_frame(&mut reader, FrameFormat::LengthPrefixed).unwrap(), Some(b"first".to_vec()));
+        assert_eq!(read_frame(&mut reader, FrameFormat::LengthPrefixed).unwrap(), Some(Vec::new()));
+        assert_eq!(read_frame(&mut reader, FrameFormat::LengthPrefixed).unwrap(), Some(b"third".to_vec()));
+        assert_eq!(read_frame(&mut reader, FrameFormat::LengthPrefixed).unwrap(), None);
+    }
+
+    #[test]
+    fn concatenated_pngs() {
+        let stream = [PNG, PNG].concat();
+        let mut reader = &stream[..];
+        assert_eq!(read_frame(&mut reader, FrameFormat::Png).unwrap().as_deref(), Some(PNG));
+        assert_eq!(read_frame(&mut reader, FrameFormat::Png).unwrap().as_deref(), Some(PNG));
+        assert_eq!(read_frame(&mut reader, FrameFormat::Png).unwrap(), None);
+    }
+
+    #[test]
+    fn empty_stream_ends_cleanly() {
+        for &format in [FrameFormat::LengthPrefixed, FrameFormat::Png].iter() {
+            assert_eq!(read_frame(&mut &b""[..], format).unwrap(), None);
+        }
+    }
+
+    #[test]
+    fn frame_cut_off_at_end() {
+        let stream = length_prefixed(&[b"frame"]);
+        for end in 1 .. stream.len() {
+            let err = read_frame(&mut &stream[.. end], FrameFormat::LengthPrefixed).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        }
+
+        let err = read_frame(&mut &PNG[.. PNG.len() - 1], FrameFormat::Png).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn oversized_frames_are_rejected() {
+        let mut stream = u32::MAX.to_be_bytes().to_vec();
+        stream.extend_from_slice(b"data");
+        let err = read_frame(&mut &stream[..], FrameFormat::LengthPrefixed).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        // the length of the first chunk after the signature
+        let mut png = PNG.to_vec();
+        png[8 .. 12].copy_from_slice(&u32::MAX.to_be_bytes());
+        let err = read_frame(&mut &png[..], FrameFormat::Png).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn other_data_is_not_a_png_stream() {
+        let err = read_frame(&mut &b"GIF89a and more"[..], FrameFormat::Png).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,169 @@
+use std::collections::HashMap;
+use std::ops::Range;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use super::error::RimError;
+use super::image::{Image, texture_stats, texture_bytes};
+use super::view::View;
+
+/// Textures are scaled down at most to this size, so evicted images still show a preview.
+const MIN_TEXTURE_SIZE: u32 = 64;
+
+/// Textures changed per frame, re-reading and scaling large images is slow.
+const MAX_UPLOADS_PER_FRAME: usize = 4;
+
+/// Bytes of the last budget created, read when new images are uploaded, see `initial_divisor`.
+static BUDGET: AtomicUsize = AtomicUsize::new(usize::MAX);
+
+/// Keeps the estimated memory of all textures below a limit. Textures of views shown smaller than the image
+/// are scaled down to the size they are shown at, least recently selected first, and further down to small previews
+/// if that isn't enough. They go back to the resolution needed when memory is available again or their view is selected.
+pub struct TextureBudget {
+    bytes   : usize,
+}
+
+struct Entry {
+    image       : Arc<Image>,
+    /// Largest divisor which still shows the image in full detail in all its views.
+    required    : u32,
+    /// The image can't be read from its file again.
+    keep_full   : bool,
+}
+
+impl TextureBudget {
+    pub fn new(bytes: usize) -> TextureBudget {
+        BUDGET.store(bytes, Ordering::Relaxed);
+        TextureBudget {
+            bytes,
+        }
+    }
+
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+
+    /// Scales the textures of `views` up or down, the views at indices outside of `shown`, e.g. in groups which
+    /// aren't shown, only need previews.
+    pub fn update(&self, views: &[View], shown: Range<usize>) -> Vec<RimError> {
+        let mut entries: Vec<Entry> = Vec::new();
+        let mut indices: HashMap<*const Image, usize> = HashMap::new();
+        for (index, view) in views.iter().enumerate() {
+            if view.selected {
+                view.image.touch();
+            }
+
+            let required = if shown.contains(&index) { required_divisor(view) } else { preview_divisor(&view.image) };
+            let required = std::cmp::max(required, view.image.min_divisor());
+            let keep_full = view.is_frozen();
+            match indices.get(&Arc::as_ptr(&view.image)) {
+                Some(&entry) => {
+                    let entry = &mut entries[entry];
+                    entry.required = std::cmp::min(entry.required, required);
+                    entry.keep_full |= keep_full;
+                },
+                None => {
+                    indices.insert(Arc::as_ptr(&view.image), entries.len());
+                    entries.push(Entry { image: view.image.clone(), required, keep_full });
+                },
+            }
+        }
+
+        let mut errors = Vec::new();
+        let mut uploads = 0;
+        let mut upload = |entry: &Entry, divisor: u32, errors: &mut Vec<RimError>| {
+            uploads += 1;
+            if let Err(err) = entry.image.set_divisor(divisor, entry.keep_full) {
+                errors.push(err);
+            }
+            uploads < MAX_UPLOADS_PER_FRAME
+        };
+
+        // most recently selected first
+        entries.sort_by_key(|entry| std::cmp::Reverse(entry.image.last_used()));
+
+        // go back up to the needed resolution while there is memory for it, the selected image always gets it
+        for (i, entry) in entries.iter().enumerate() {
+            let divisor = entry.image.divisor();
+            if divisor <= entry.required {
+                continue;
+            }
+
+            let extra = texture_size(&entry.image, entry.required).saturating_sub(texture_size(&entry.image, divisor));
+            if i > 0 && texture_stats().bytes + extra > self.bytes {
+                continue;
+            }
+            if !upload(entry, entry.required, &mut errors) {
+                return errors;
+            }
+        }
+
+        // scale down the least recently selected, first to the size they are shown at, then to previews
+        for down_to_preview in [false, true].iter() {
+            // the selected image keeps its resolution, even if it alone is over the budget
+            let skip = if *down_to_preview { 1 } else { 0 };
+            for entry in entries.iter().skip(skip).rev() {
+                if texture_stats().bytes <= self.bytes {
+                    return errors;
+                }
+
+                let divisor = if *down_to_preview {
+                    preview_divisor(&entry.image)
+                } else {
+                    entry.required
+                };
+                if divisor <= entry.image.divisor() {
+                    continue;
+                }
+                if !upload(entry, divisor, &mut errors) {
+                    return errors;
+                }
+            }
+        }
+
+        errors
+    }
+}
+
+/// Largest power of two the image can be scaled down by and still have at least as many pixels as its view.
+fn required_divisor(view: &View) -> u32 {
+    let shown_width = (view.width as f32 * view.zoom).max(1.0);
+    let shown_height = (view.height as f32 * view.zoom).max(1.0);
+
+    let mut divisor = 1;
+    while (view.image.width as f32 / (divisor * 2) as f32) >= shown_width
+        && (view.image.height as f32 / (divisor * 2) as f32) >= shown_height {
+        divisor *= 2;
+    }
+    divisor
+}
+
+fn preview_divisor(image: &Image) -> u32 {
+    max_divisor(image.width as u32, image.height as u32)
+}
+
+/// Largest power of two an image of this size can be scaled down by and still be a preview.
+fn max_divisor(width: u32, height: u32) -> u32 {
+    let mut divisor = 1;
+    while std::cmp::max(width, height) / (divisor * 2) >= MIN_TEXTURE_SIZE {
+        divisor *= 2;
+    }
+    divisor
+}
+
+/// Smallest power of two a new image of this size has to be scaled down by to fit into what is left of the budget,
+/// at most down to a preview. Opening many large images at once then doesn't upload them at full resolution first,
+/// `TextureBudget::update` scales them back up once they are shown and there is memory for it.
+pub fn initial_divisor(width: u32, height: u32) -> u32 {
+    let available = BUDGET.load(Ordering::Relaxed).saturating_sub(texture_stats().bytes);
+    let max = max_divisor(width, height);
+    let mut divisor = 1;
+    while divisor < max && texture_bytes((width / divisor) as usize, (height / divisor) as usize, 4) > available {
+        divisor *= 2;
+    }
+    divisor
+}
+
+fn texture_size(image: &Image, divisor: u32) -> usize {
+    texture_bytes(image.width / divisor as usize, image.height / divisor as usize, 4)
+}
@@ -0,0 +1,155 @@
+use std::time::{Duration, Instant};
+
+use super::error::RimError;
+use super::image::{texture_created, texture_deleted};
+use super::renderer::{self, TextureFilter};
+use super::vec::Vec2;
+
+/// Side length of a tile in pixels, if the maximum texture size isn't smaller.
+const TILE_SIZE: u32 = 2048;
+
+/// Tiles which weren't visible for this long are deleted.
+const TILE_LIFETIME: Duration = Duration::from_secs(5);
+
+/// Tiles uploaded per frame, the others show the scaled down texture until later frames.
+const MAX_UPLOADS_PER_FRAME: usize = 2;
+
+/// Part of the image with its own texture, in pixels of the full resolution image.
+pub struct TileView {
+    pub texture : usize,
+    pub x       : f32,
+    pub y       : f32,
+    pub width   : f32,
+    pub height  : f32,
+}
+
+struct Tile {
+    x           : u32,
+    y           : u32,
+    width       : u32,
+    height      : u32,
+    texture     : Option<usize>,
+    last_used   : Instant,
+}
+
+/// The full resolution of an image larger than the maximum texture size, split into tiles.
+/// Only tiles which are visible get textures, and lose them again once they aren't visible for a while.
+pub struct TileSet {
+    tiles   : Vec<Tile>,
+}
+
+impl TileSet {
+    pub fn new(width: u32, height: u32, max_texture_size: u32) -> TileSet {
+        let tile_size = std::cmp::min(TILE_SIZE, max_texture_size);
+        let mut tiles = Vec::new();
+        for y in (0..height).step_by(tile_size as usize) {
+            for x in (0..width).step_by(tile_size as usize) {
+                tiles.push(Tile {
+                    x,
+                    y,
+                    width       : std::cmp::min(tile_size, width - x),
+                    height      : std::cmp::min(tile_size, height - y),
+                    texture     : None,
+                    last_used   : Instant::now(),
+                });
+            }
+        }
+
+        TileSet {
+            tiles,
+        }
+    }
+
+    /// Returns the uploaded tiles overlapping the region from `min` to `max` and uploads missing ones from `pixels`.
+    /// `filter` is the minification and magnification filter of new tiles.
+    pub fn visible(&mut self, pixels: &image::DynamicImage, min: Vec2, max: Vec2, filter: (TextureFilter, TextureFilter)) -> Vec<TileView> {
+        let now = Instant::now();
+        let mut uploads = 0;
+        let mut visible = Vec::new();
+
+        for tile in self.tiles.iter_mut() {
+            let overlaps = (tile.x as f32) < max.x && ((tile.x + tile.width) as f32) > min.x
+                && (tile.y as f32) < max.y && ((tile.y + tile.height) as f32) > min.y;
+
+            if !overlaps {
+                if tile.texture.is_some() && now.duration_since(tile.last_used) > TILE_LIFETIME {
+                    tile.delete_texture();
+                }
+                continue;
+            }
+
+            tile.last_used = now;
+            if tile.texture.is_none() {
+                if uploads >= MAX_UPLOADS_PER_FRAME {
+                    continue;
+                }
+                uploads += 1;
+                tile.upload(pixels, filter);
+            }
+            let texture = match tile.texture {
+                Some(texture) => texture,
+                None => continue,
+            };
+
+            visible.push(TileView {
+                texture,
+                x       : tile.x as f32,
+                y       : tile.y as f32,
+                width   : tile.width as f32,
+                height  : tile.height as f32,
+            });
+        }
+
+        visible
+    }
+
+    /// Applies new filters to all uploaded tiles.
+    pub fn set_filter(&self, filter: (TextureFilter, TextureFilter)) {
+        renderer::with(|renderer| {
+            for texture in self.tiles.iter().filter_map(|tile| tile.texture) {
+                renderer.set_filter(texture, filter.0, filter.1);
+            }
+        });
+    }
+}
+
+impl Tile {
+    fn upload(&mut self, pixels: &image::DynamicImage, filter: (TextureFilter, TextureFilter)) {
+        let data = pixels.crop_imm(self.x, self.y, self.width, self.height).to_rgba();
+
+        let result: Result<usize, RimError> = renderer::with(|renderer| {
+            let texture = renderer.create_texture()?;
+            renderer.set_filter(texture, filter.0, filter.1);
+            renderer.upload_texture(texture, 0, &data);
+            Ok(texture)
+        });
+        match result {
+            Ok(texture) => {
+                self.texture = Some(texture);
+                texture_created(self.bytes());
+            },
+            Err(err) => log::error!("Failed to create a texture for a tile: {}", err),
+        }
+    }
+
+    fn delete_texture(&mut self) {
+        if let Some(texture) = self.texture.take() {
+            if renderer::has_current() {
+                renderer::with(|renderer| renderer.delete_texture(texture));
+            }
+            texture_deleted(self.bytes());
+        }
+    }
+
+    fn bytes(&self) -> usize {
+        self.width as usize * self.height as usize * 4
+    }
+}
+
+impl Drop for TileSet {
+    fn drop(&mut self) {
+        for tile in self.tiles.iter_mut() {
+            tile.delete_texture();
+        }
+    }
+}
@@ -1,8 +1,12 @@
 use std::path::*;
 
+use super::error::RimError;
 
-pub fn get_absolute_path(path: &Path) -> PathBuf {
-    let path = path.canonicalize().unwrap();
+pub fn get_absolute_path(path: &Path) -> Result<PathBuf, RimError> {
+    let path = match path.canonicalize() {
+        Ok(path) => path,
+        Err(err) => return Err(RimError::io(path, err)),
+    };
     // if path.starts_with("\\\\?\\") {
-    PathBuf::from(path.to_str().unwrap().replace("\\\\?\\", ""))
-}
\ No newline at end of file
+    Ok(PathBuf::from(path.to_string_lossy().replace("\\\\?\\", "")))
+}
@@ -1,7 +1,12 @@
-use std::rc::Rc;
+use std::sync::Arc;
 use imgui::im_str;
+use serde::{Deserialize, Serialize};
 
+use super::action::Action;
+use super::error::RimError;
 use super::image::Image;
+use super::keymap::KeyMap;
+use super::renderer::TextureFilter;
 use super::vec::Vec2;
 
 fn clamp(f: f32, min: f32, max: f32) -> f32 {
@@ -34,10 +39,15 @@ macro_rules! defer {
     };
 }
 
-#[derive(Debug,Copy,Clone,PartialEq)]
+#[derive(Debug,Copy,Clone,PartialEq,Serialize,Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum FilterMethod {
     Nearest,
-    Linear
+    Linear,
+    /// Interpolates between mipmaps when shown smaller, keeps pixels sharp when shown larger.
+    Trilinear,
+    /// Like `Trilinear`, with mipmaps scaled down by a Lanczos filter for the best quality when shown smaller.
+    Lanczos,
 }
 
 pub struct View {
@@ -46,12 +56,12 @@ pub struct View {
     pub y           : i32,
     pub width       : i32,
     pub height      : i32,
-    pub image       : Rc<Image>,
+    pub image       : Arc<Image>,
 
     pub filter_method : FilterMethod,
 
-    rect_pos        : Vec2,
-    zoom            : f32,
+    pub rect_pos    : Vec2,
+    pub zoom        : f32,
 
     pan_speed       : f32,
     zoom_speed      : f32,
@@ -60,10 +70,13 @@ pub struct View {
 
     frozen              : bool,
     pub history_enabled : bool,
+
+    /// Why the last reload failed, shown as a badge until a reload succeeds.
+    pub error           : Option<String>,
 }
 
 impl View {
-    pub fn new(id: u32, image: Rc<Image>, enable_history: bool) -> View {
+    pub fn new(id: u32, image: Arc<Image>, enable_history: bool) -> View {
         View {
             id              : id,
             x               : 0,
@@ -84,6 +97,8 @@ impl View {
 
             frozen          : false,
             history_enabled : enable_history,
+
+            error           : None,
         }
     }
 
@@ -93,10 +108,16 @@ impl View {
 
     pub fn freeze(&mut self) {
         self.frozen = true;
+        self.image.freeze();
+    }
+
+    pub fn reset_zoom(&mut self) {
+        self.zoom = 1.0;
+        self.rect_pos = Vec2::zero();
     }
 
-    pub fn render(&mut self, ui: &imgui::Ui, title_bar: bool, focus: bool) -> bool {
-        let title: String = self.image.path.to_str().unwrap().to_owned();
+    pub fn render(&mut self, ui: &imgui::Ui, title_bar: bool, focus: bool, keymap: &KeyMap) -> bool {
+        let title = self.image.path.to_string_lossy();
         let title = if self.frozen {
             im_str!("{} - past##{}", title, self.id)
         } else {
@@ -108,6 +129,19 @@ impl View {
 
         let mut was_selected = false;
 
+        let error = self.error.clone();
+        let draw_error_badge = |ui: &imgui::Ui, content_region_width: f32| {
+            if let Some(error) = &error {
+                let tok = ui.push_style_color(imgui::StyleColor::Button, [0.8, 0.1, 0.1, 1.0]);
+                ui.set_cursor_pos([content_region_width - 24.0, 4.0]);
+                ui.small_button(im_str!(" ! "));
+                if ui.is_item_hovered() {
+                    ui.tooltip_text(error);
+                }
+                tok.pop(ui);
+            }
+        };
+
         imgui::Window::new(&title)
             .focus_on_appearing(false)
             .focused(self.selected && focus)
@@ -134,54 +168,25 @@ impl View {
                 let [content_region_width, content_region_height] = ui.content_region_avail();
                 let content_region_as = content_region_width / content_region_height;
                 let win = ui.is_key_down(sdl2::keyboard::Scancode::Application as u32);
-                let ctrl = ui.is_key_down(sdl2::keyboard::Scancode::LCtrl as u32) || ui.is_key_down(sdl2::keyboard::Scancode::RCtrl as u32);
-                let shift = ui.is_key_down(sdl2::keyboard::Scancode::LShift as u32) || ui.is_key_down(sdl2::keyboard::Scancode::RShift as u32);
-                
+
                 if ui.is_window_focused() && self.selected && !win {
-                    if !ctrl {
-                        if ui.is_key_pressed(sdl2::keyboard::Scancode::Space as u32) {
-                            self.zoom = 1.0;
-                            self.rect_pos = Vec2::zero();
-                        }
-                        if ui.is_key_down(sdl2::keyboard::Scancode::W as u32) {
-                            self.rect_pos = self.rect_pos + Vec2::new(0.0, -self.pan_speed / self.zoom);
-                        }
-                        if ui.is_key_down(sdl2::keyboard::Scancode::S as u32) {
-                            self.rect_pos = self.rect_pos + Vec2::new(0.0, self.pan_speed / self.zoom);
-                        }
-                        if ui.is_key_down(sdl2::keyboard::Scancode::A as u32) {
-                            self.rect_pos = self.rect_pos + Vec2::new(-self.pan_speed / self.zoom, 0.0);
-                        }
-                        if ui.is_key_down(sdl2::keyboard::Scancode::D as u32) {
-                            self.rect_pos = self.rect_pos + Vec2::new(self.pan_speed / self.zoom, 0.0);
-                        }
-                        if ui.is_key_down(sdl2::keyboard::Scancode::Period as u32) {
-                            self.zoom *= 1.0 + self.zoom_speed * 0.01;
-                        }
-                        if ui.is_key_down(sdl2::keyboard::Scancode::Comma as u32) {
-                            self.zoom /= 1.0 + self.zoom_speed * 0.01;
-                        }
-                        if shift {
-                            if ui.is_key_down(sdl2::keyboard::Scancode::Up as u32) {
-                                self.zoom *= 1.0 + self.zoom_speed * 0.01;
-                            }
-                            if ui.is_key_down(sdl2::keyboard::Scancode::Down as u32) {
-                                self.zoom /= 1.0 + self.zoom_speed * 0.01;
-                            }
-                        } else {
-                            if ui.is_key_down(sdl2::keyboard::Scancode::Up as u32) {
-                                self.rect_pos = self.rect_pos + Vec2::new(0.0, -self.pan_speed / self.zoom);
-                            }
-                            if ui.is_key_down(sdl2::keyboard::Scancode::Down as u32) {
-                                self.rect_pos = self.rect_pos + Vec2::new(0.0, self.pan_speed / self.zoom);
-                            }
-                            if ui.is_key_down(sdl2::keyboard::Scancode::Left as u32) {
-                                self.rect_pos = self.rect_pos + Vec2::new(-self.pan_speed / self.zoom, 0.0);
-                            }
-                            if ui.is_key_down(sdl2::keyboard::Scancode::Right as u32) {
-                                self.rect_pos = self.rect_pos + Vec2::new(self.pan_speed / self.zoom, 0.0);
-                            }
-                        }
+                    if keymap.is_held(ui, Action::PanUp) {
+                        self.rect_pos = self.rect_pos + Vec2::new(0.0, -self.pan_speed / self.zoom);
+                    }
+                    if keymap.is_held(ui, Action::PanDown) {
+                        self.rect_pos = self.rect_pos + Vec2::new(0.0, self.pan_speed / self.zoom);
+                    }
+                    if keymap.is_held(ui, Action::PanLeft) {
+                        self.rect_pos = self.rect_pos + Vec2::new(-self.pan_speed / self.zoom, 0.0);
+                    }
+                    if keymap.is_held(ui, Action::PanRight) {
+                        self.rect_pos = self.rect_pos + Vec2::new(self.pan_speed / self.zoom, 0.0);
+                    }
+                    if keymap.is_held(ui, Action::ZoomIn) {
+                        self.zoom *= 1.0 + self.zoom_speed * 0.01;
+                    }
+                    if keymap.is_held(ui, Action::ZoomOut) {
+                        self.zoom /= 1.0 + self.zoom_speed * 0.01;
                     }
                 }
 
@@ -208,6 +213,7 @@ impl View {
                 let mut uv1 = Vec2::new(1.0, 1.0);
                 
                 if rect_max.x <= 0.0 || rect_max.y <= 0.0 || rect_min.x >= content_region_width || rect_min.y >= content_region_height {
+                    draw_error_badge(ui, content_region_width);
                     return;
                 }
 
@@ -230,7 +236,8 @@ impl View {
                     rect_max.y = content_region_max[1] - border - 1.0;
                 }
 
-                let pos = (rect_min + ui.window_content_region_min().into()).into();
+                let content_min: Vec2 = ui.window_content_region_min().into();
+                let pos = (rect_min + content_min).into();
                 let size = rect_max - rect_min;
                 ui.set_cursor_pos(pos);
                 unsafe {
@@ -239,30 +246,82 @@ impl View {
                         .uv1(uv1.into())
                         .build(&ui);
                 }
+
+                // the texture of images too large for one is scaled down, draw the tiles on top once it has fewer pixels than shown
+                if self.image.is_tiled() {
+                    let image_size = Vec2::new(self.image.width as f32, self.image.height as f32);
+                    let region_min = uv0 * image_size;
+                    let region_max = uv1 * image_size;
+                    let scale = size / (region_max - region_min);
+
+                    if scale.x * self.image.divisor() as f32 > 1.0 {
+                        for tile in self.image.visible_tiles(region_min, region_max) {
+                            let tile_min = Vec2::new(tile.x.max(region_min.x), tile.y.max(region_min.y));
+                            let tile_max = Vec2::new((tile.x + tile.width).min(region_max.x), (tile.y + tile.height).min(region_max.y));
+                            let tile_pos = Vec2::new(tile.x, tile.y);
+                            let tile_size = Vec2::new(tile.width, tile.height);
+
+                            ui.set_cursor_pos((rect_min + content_min + (tile_min - region_min) * scale).into());
+                            unsafe {
+                                imgui::Image::new(std::mem::transmute(tile.texture), ((tile_max - tile_min) * scale).into())
+                                    .uv0(((tile_min - tile_pos) / tile_size).into())
+                                    .uv1(((tile_max - tile_pos) / tile_size).into())
+                                    .build(&ui);
+                            }
+                        }
+                    }
+                }
+
+                draw_error_badge(ui, content_region_width);
             });
 
         return was_selected;
     }
 
-    pub fn set_filter_menthod(&mut self, filter_method: FilterMethod) {
+    /// Sets the filter of the texture. The texture of an image shown by other views is copied first if they use
+    /// other filters, so they keep theirs.
+    pub fn set_filter_menthod(&mut self, filter_method: FilterMethod) -> Result<(), RimError> {
         self.filter_method = filter_method;
-        GL!(BindTexture(TEXTURE_2D, self.image.renderer_id as u32));
-        
-        let filter_method = match self.filter_method {
-            FilterMethod::Linear => gl::LINEAR,
-            FilterMethod::Nearest => gl::NEAREST,
-        } as i32;
-        
-        GL!(TexParameteri(TEXTURE_2D, TEXTURE_MIN_FILTER, filter_method));
-        GL!(TexParameteri(TEXTURE_2D, TEXTURE_MAG_FILTER, filter_method));
-        GL!(BindTexture(TEXTURE_2D, 0));
+        let lanczos = filter_method == FilterMethod::Lanczos;
+        let (min_filter, mag_filter) = match self.filter_method {
+            FilterMethod::Linear => (TextureFilter::Linear, TextureFilter::Linear),
+            FilterMethod::Nearest => (TextureFilter::Nearest, TextureFilter::Nearest),
+            FilterMethod::Trilinear | FilterMethod::Lanczos => (TextureFilter::LinearMipmapLinear, TextureFilter::Nearest),
+        };
+
+        if Arc::strong_count(&self.image) > 1 && !self.image.has_filter(min_filter, mag_filter, lanczos) {
+            self.image = self.image.duplicate()?;
+        }
+
+        self.image.set_lanczos_mipmaps(lanczos)?;
+        self.image.set_filter(min_filter, mag_filter);
+        Ok(())
+    }
+
+    pub fn reload(&mut self) -> Result<(), RimError> {
+        if !self.frozen {
+            let result = self.image.reload_from_disk();
+            self.track_error(result)?;
+        }
+
+        Ok(())
     }
 
-    pub fn reload(&self) -> Result<(), String> {
+    pub fn reload_from_memory(&mut self, data: &[u8]) -> Result<(), RimError> {
         if !self.frozen {
-            self.image.reload_from_disk()?;
+            let result = self.image.reload_from_memory(data);
+            self.track_error(result)?;
         }
 
         Ok(())
     }
+
+    /// Shows the error of a failed reload on the view, a successful reload removes it.
+    pub fn track_error(&mut self, result: Result<(), RimError>) -> Result<(), RimError> {
+        self.error = match &result {
+            Ok(_) => None,
+            Err(err) => Some(err.to_string()),
+        };
+        result
+    }
 }
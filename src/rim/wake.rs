@@ -0,0 +1,47 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::mpsc::{channel, Receiver};
+
+/// Type of the SDL user event which wakes the main loop, 0 until it is registered.
+static WAKE_EVENT: AtomicU32 = AtomicU32::new(0);
+
+/// Registers the event pushed by `wake`. Without it, e.g. in headless apps, `wake` does nothing.
+pub fn register(events: &sdl2::EventSubsystem) -> Result<(), String> {
+    let event_type = unsafe { events.register_event()? };
+    WAKE_EVENT.store(event_type, Ordering::Relaxed);
+    Ok(())
+}
+
+pub fn is_registered() -> bool {
+    WAKE_EVENT.load(Ordering::Relaxed) != 0
+}
+
+/// Wakes the main loop while it waits for events, so results of background threads are handled without delay.
+/// Can be called from any thread.
+pub fn wake() {
+    let event_type = WAKE_EVENT.load(Ordering::Relaxed);
+    if event_type == 0 {
+        return;
+    }
+
+    // SDL_PushEvent is thread safe, unlike the event subsystem of sdl2
+    unsafe {
+        let mut event: sdl2::sys::SDL_Event = std::mem::zeroed();
+        event.user.type_ = event_type;
+        sdl2::sys::SDL_PushEvent(&mut event);
+    }
+}
+
+/// Passes everything sent to `receiver` on to the returned receiver and wakes the main loop for it,
+/// for senders which can't call `wake` themselves, e.g. the file watcher.
+pub fn forward<T: Send + 'static>(receiver: Receiver<T>) -> Receiver<T> {
+    let (sender, forwarded) = channel();
+    std::thread::spawn(move || {
+        for item in receiver {
+            if sender.send(item).is_err() {
+                break;
+            }
+            wake();
+        }
+    });
+    forwarded
+}